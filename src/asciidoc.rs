@@ -0,0 +1,115 @@
+use crate::{
+    fields::{Cell, Field, Style},
+    Repo, Totals,
+};
+use std::io::{Result, Write};
+
+/// escapes the cell separator, which would otherwise start a new cell
+fn escape(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+/// numbers are right aligned so their digits line up
+fn align(cell: &Cell) -> &'static str {
+    match cell {
+        Cell::Text(_) => "<",
+        _ => ">",
+    }
+}
+
+/// writes an asciidoc table with a header row of field names, one row per
+/// repository, and a footer row of total costs
+pub fn write(
+    writer: &mut impl Write,
+    fields: &[Field],
+    style: &Style,
+    repos: &[Repo],
+    totals: &Totals,
+) -> Result<()> {
+    let cols = fields
+        .iter()
+        .map(|field| {
+            repos
+                .first()
+                .map(|repo| align(&field.value(repo, style)))
+                .unwrap_or("<")
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    writeln!(writer, "[cols=\"{}\", options=\"header,footer\"]", cols)?;
+    writeln!(writer, "|===")?;
+    writeln!(
+        writer,
+        "{}",
+        fields
+            .iter()
+            .map(|field| format!("|{}", escape(&field.column(style))))
+            .collect::<Vec<_>>()
+            .join(" ")
+    )?;
+    for repo in repos {
+        writeln!(writer)?;
+        for field in fields {
+            writeln!(writer, "|{}", escape(&field.value(repo, style).to_string()))?;
+        }
+    }
+    writeln!(writer)?;
+    for (index, field) in fields.iter().enumerate() {
+        let total = match field {
+            Field::Cost => style.cost(totals.monthly_cost).to_string(),
+            Field::CappedCost => style.cost(totals.monthly_capped_cost).to_string(),
+            _ if index == 0 => "total".into(),
+            _ => String::new(),
+        };
+        writeln!(writer, "|{}", total)?;
+    }
+    writeln!(writer, "|===")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cell_separators_are_escaped() {
+        assert_eq!(escape("team/api"), "team/api");
+        assert_eq!(escape("a|b||c"), "a\\|b\\|\\|c");
+    }
+
+    #[test]
+    fn numbers_are_right_aligned() {
+        assert_eq!(align(&Cell::Text(None)), "<");
+        assert_eq!(align(&Cell::Integer(1)), ">");
+        assert_eq!(align(&Cell::Cost(1.0)), ">");
+    }
+
+    #[test]
+    fn writes_a_table_with_a_total_footer() {
+        let repo = crate::tests::repo("a|b");
+        let totals = Totals::default().add(&repo);
+        let mut out = Vec::new();
+        write(
+            &mut out,
+            &[Field::Name, Field::Images, Field::Cost],
+            &Style::default(),
+            &[repo],
+            &totals,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[cols=\"<,>,>\", options=\"header,footer\"]\n\
+             |===\n\
+             |name |hosted_images |monthly_cost\n\
+             \n\
+             |a\\|b\n\
+             |2\n\
+             |$0.30\n\
+             \n\
+             |total\n\
+             |\n\
+             |$0.30\n\
+             |===\n"
+        );
+    }
+}
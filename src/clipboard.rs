@@ -0,0 +1,66 @@
+use arboard::Clipboard;
+
+/// set in the environment of the process `copy` spawns to hold the clipboard.
+/// x11 and wayland clipboards are served by the process owning them, so they
+/// would empty as soon as this one exits
+#[cfg(all(
+    unix,
+    not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))
+))]
+pub const HOLDER: &str = "ECR_INSIGHTS_CLIPBOARD_HOLDER";
+
+/// puts text on the system clipboard, where it stays after this process exits
+#[cfg(all(
+    unix,
+    not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))
+))]
+pub fn copy(text: &[u8]) -> Result<(), String> {
+    use std::{
+        env,
+        io::Write,
+        process::{Command, Stdio},
+    };
+    // fail here, where it can be reported, when there's no clipboard at all
+    Clipboard::new().map_err(|err| err.to_string())?;
+    let mut holder = Command::new(env::current_exe().map_err(|err| err.to_string())?)
+        .env(HOLDER, "1")
+        .stdin(Stdio::piped())
+        // closed so a pipe we're writing to doesn't wait on the holder
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| format!("failed to start a process to hold the clipboard: {}", err))?;
+    if let Some(mut stdin) = holder.stdin.take() {
+        stdin.write_all(text).map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+/// serves the text on stdin from the clipboard until something else is copied,
+/// as the process `copy` spawns
+#[cfg(all(
+    unix,
+    not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))
+))]
+pub fn hold() -> Result<(), Box<dyn std::error::Error>> {
+    use arboard::SetExtLinux;
+    use std::io::{stdin, Read};
+    let mut text = Vec::new();
+    stdin().read_to_end(&mut text)?;
+    Ok(Clipboard::new()?
+        .set()
+        .wait()
+        .text(String::from_utf8_lossy(&text))?)
+}
+
+/// puts text on the system clipboard, which outlives this process on macos and
+/// windows
+#[cfg(not(all(
+    unix,
+    not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))
+)))]
+pub fn copy(text: &[u8]) -> Result<(), String> {
+    Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(String::from_utf8_lossy(text)))
+        .map_err(|err| err.to_string())
+}
@@ -0,0 +1,188 @@
+use crate::pushed_at;
+use chrono::{Months, NaiveDateTime};
+use rusoto_ecr::ImageDetail;
+use std::str::FromStr;
+
+/// how each of a repository's images translates into bytes billed for a
+/// month, which are summed for the repository and `Pricing` then turns into a
+/// cost. images are billed one at a time so repositories can be summed as
+/// their pages of images arrive
+pub trait CostModel {
+    /// bytes of the image billed for the month starting at `month_start`, a
+    /// fraction of its size when it was hosted for part of the month
+    fn billed_bytes(
+        &self,
+        details: &ImageDetail,
+        month_start: NaiveDateTime,
+    ) -> f64;
+}
+
+/// bills the images hosted at the start of the month for the whole month,
+/// ignoring anything pushed since
+pub struct Snapshot;
+
+impl CostModel for Snapshot {
+    fn billed_bytes(
+        &self,
+        details: &ImageDetail,
+        month_start: NaiveDateTime,
+    ) -> f64 {
+        if pushed_at(details) < month_start {
+            details.image_size_in_bytes.unwrap_or_default() as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+/// bills hosted images for the whole month as `Snapshot` does, plus images
+/// pushed this month for the share of it remaining after their push, assuming
+/// nothing is deleted before the month ends
+pub struct Prorated;
+
+impl CostModel for Prorated {
+    fn billed_bytes(
+        &self,
+        details: &ImageDetail,
+        month_start: NaiveDateTime,
+    ) -> f64 {
+        let month_end = month_start
+            .checked_add_months(Months::new(1))
+            .unwrap_or(month_start);
+        let month = (month_end - month_start).num_seconds().max(1) as f64;
+        let size = details.image_size_in_bytes.unwrap_or_default() as f64;
+        let pushed = pushed_at(details);
+        if pushed < month_start {
+            size
+        } else {
+            size * (month_end - pushed).num_seconds().max(0) as f64 / month
+        }
+    }
+}
+
+/// cost models selectable with `--cost-model`
+#[derive(Clone, Copy)]
+pub enum Model {
+    Snapshot,
+    Prorated,
+}
+
+impl Model {
+    pub fn strategy(self) -> &'static dyn CostModel {
+        match self {
+            Model::Snapshot => &Snapshot,
+            Model::Prorated => &Prorated,
+        }
+    }
+}
+
+impl FromStr for Model {
+    type Err = String;
+
+    fn from_str(model: &str) -> Result<Self, Self::Err> {
+        match model {
+            "snapshot" => Ok(Model::Snapshot),
+            "prorated" => Ok(Model::Prorated),
+            other => Err(format!(
+                "unknown cost model {}, expected one of snapshot or prorated",
+                other
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(
+        month: u32,
+        day: u32,
+        hour: u32,
+    ) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, month, day)
+            .and_then(|date| date.and_hms_opt(hour, 0, 0))
+            .unwrap()
+    }
+
+    fn pushed(
+        size: i64,
+        pushed_at: NaiveDateTime,
+    ) -> ImageDetail {
+        ImageDetail {
+            image_size_in_bytes: Some(size),
+            image_pushed_at: Some(pushed_at.and_utc().timestamp() as f64),
+            ..ImageDetail::default()
+        }
+    }
+
+    #[test]
+    fn snapshot_bills_images_hosted_at_the_month_start() {
+        let march = at(3, 1, 0);
+        assert_eq!(
+            Snapshot.billed_bytes(&pushed(100, at(2, 20, 0)), march),
+            100.0
+        );
+        assert_eq!(Snapshot.billed_bytes(&pushed(100, march), march), 0.0);
+        assert_eq!(
+            Snapshot.billed_bytes(&pushed(100, at(3, 16, 0)), march),
+            0.0
+        );
+    }
+
+    #[test]
+    fn prorated_bills_hosted_images_in_full() {
+        let march = at(3, 1, 0);
+        assert_eq!(
+            Prorated.billed_bytes(&pushed(100, at(2, 20, 0)), march),
+            100.0
+        );
+        assert_eq!(Prorated.billed_bytes(&pushed(100, march), march), 100.0);
+    }
+
+    #[test]
+    fn prorated_bills_the_rest_of_the_month_after_a_push() {
+        // march has 31 days, so half of it remains at noon on the 16th
+        let march = at(3, 1, 0);
+        assert_eq!(
+            Prorated.billed_bytes(&pushed(100, at(3, 16, 12)), march),
+            50.0
+        );
+        // february 2024 has 29 days
+        assert_eq!(
+            Prorated.billed_bytes(&pushed(290, at(2, 28, 0)), at(2, 1, 0)),
+            20.0
+        );
+    }
+
+    #[test]
+    fn prorated_bills_nothing_for_pushes_after_the_month() {
+        let march = at(3, 1, 0);
+        assert_eq!(Prorated.billed_bytes(&pushed(100, at(4, 1, 0)), march), 0.0);
+        assert_eq!(
+            Prorated.billed_bytes(&pushed(100, at(4, 20, 0)), march),
+            0.0
+        );
+    }
+
+    #[test]
+    fn images_without_a_size_bill_nothing() {
+        let details = ImageDetail {
+            image_size_in_bytes: None,
+            ..pushed(0, at(2, 1, 0))
+        };
+        assert_eq!(Snapshot.billed_bytes(&details, at(3, 1, 0)), 0.0);
+        assert_eq!(Prorated.billed_bytes(&details, at(3, 1, 0)), 0.0);
+    }
+
+    #[test]
+    fn models_parse_by_name() {
+        assert!(matches!("snapshot".parse(), Ok(Model::Snapshot)));
+        assert!(matches!("prorated".parse(), Ok(Model::Prorated)));
+        assert_eq!(
+            "daily".parse::<Model>().err().unwrap(),
+            "unknown cost model daily, expected one of snapshot or prorated"
+        );
+    }
+}
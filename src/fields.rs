@@ -0,0 +1,566 @@
+use crate::{format, Repo};
+use serde_json::Value;
+use std::{fmt, str::FromStr};
+
+/// A column of the per repository report, selectable with `--fields`
+#[derive(Clone, Copy, PartialEq)]
+pub enum Field {
+    Account,
+    Name,
+    RawName,
+    Arn,
+    LastPushed,
+    AgeDays,
+    Size,
+    SizeDelta,
+    AggregateSize,
+    ArtifactSize,
+    CappedSize,
+    LargestImage,
+    LargestImageSize,
+    MinSize,
+    MedianSize,
+    MaxSize,
+    SizeStddev,
+    ReconciledSize,
+    SizeDiscrepancy,
+    Images,
+    Cost,
+    CappedCost,
+    CostPerImage,
+    CostFormula,
+    Authors,
+    Layers,
+    PercentOfTotal,
+    LatestOnly,
+    Sampled,
+    LatestCost,
+    HistoryCost,
+}
+
+/// A typed cell value, rendered differently by each output format
+pub enum Cell {
+    Text(Option<String>),
+    Integer(i64),
+    /// a size in bytes, optionally rounded up to a whole number of units
+    Size(i64, Option<Unit>),
+    Cost(f64),
+    /// a cost in whole thousandths of a cent, with `--units millicents`
+    Millicents(i64),
+    /// a measurement which may be unavailable, shown as a dash
+    Number(Option<f64>),
+    /// a whole number which may be unavailable, shown as a dash
+    Count(Option<i64>),
+    /// a share of a total, unavailable when the total is zero
+    Percent(Option<f64>),
+}
+
+impl Cell {
+    pub fn to_json(&self) -> Value {
+        match self {
+            Cell::Text(text) => text.clone().map(Value::String).unwrap_or(Value::Null),
+            Cell::Integer(integer) => (*integer).into(),
+            Cell::Size(bytes, unit) => unit
+                .map(|unit| unit.round_up(*bytes))
+                .unwrap_or(*bytes)
+                .into(),
+            Cell::Cost(cost) => (*cost).into(),
+            Cell::Millicents(millicents) => (*millicents).into(),
+            Cell::Number(number) | Cell::Percent(number) => {
+                number.map(Value::from).unwrap_or(Value::Null)
+            }
+            Cell::Count(count) => count.map(Value::from).unwrap_or(Value::Null),
+        }
+    }
+}
+
+/// binary units sizes can be rounded up to with `--round-to`
+#[derive(Clone, Copy)]
+pub enum Unit {
+    Kib,
+    Mib,
+    Gib,
+    Tib,
+}
+
+impl Unit {
+    fn bytes(self) -> i64 {
+        match self {
+            Unit::Kib => 1 << 10,
+            Unit::Mib => 1 << 20,
+            Unit::Gib => 1 << 30,
+            Unit::Tib => 1 << 40,
+        }
+    }
+
+    /// the number of whole units needed to hold a size
+    pub fn round_up(
+        self,
+        bytes: i64,
+    ) -> i64 {
+        (bytes + self.bytes() - 1).div_euclid(self.bytes())
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Unit::Kib => "KiB",
+            Unit::Mib => "MiB",
+            Unit::Gib => "GiB",
+            Unit::Tib => "TiB",
+        }
+    }
+}
+
+impl FromStr for Unit {
+    type Err = String;
+
+    fn from_str(unit: &str) -> Result<Self, Self::Err> {
+        match &unit.to_lowercase()[..] {
+            "kib" => Ok(Unit::Kib),
+            "mib" => Ok(Unit::Mib),
+            "gib" => Ok(Unit::Gib),
+            "tib" => Ok(Unit::Tib),
+            _ => Err(format!(
+                "unknown unit {}, expected one of kib, mib, gib, or tib",
+                unit
+            )),
+        }
+    }
+}
+
+/// what costs are reported in, selected with `--units`
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Units {
+    /// formatted as `$1.23`
+    #[default]
+    Dollars,
+    /// integer thousandths of a cent, so tiny costs don't round to $0.00
+    Millicents,
+}
+
+impl FromStr for Units {
+    type Err = String;
+
+    fn from_str(units: &str) -> Result<Self, Self::Err> {
+        match units {
+            "dollars" => Ok(Units::Dollars),
+            "millicents" => Ok(Units::Millicents),
+            other => Err(format!(
+                "unknown units {}, expected one of dollars or millicents",
+                other
+            )),
+        }
+    }
+}
+
+/// presentation options applied when rendering cells
+#[derive(Clone, Default)]
+pub struct Style {
+    /// unit to round sizes up to, raw bytes when absent
+    pub round_to: Option<Unit>,
+    pub units: Units,
+    /// account grand total monthly cost, which `percent_of_total` is a share of
+    pub total_cost: f64,
+}
+
+impl fmt::Display for Cell {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        match self {
+            Cell::Text(text) => f.write_str(text.as_deref().unwrap_or_default()),
+            Cell::Integer(integer) => write!(f, "{}", integer),
+            Cell::Size(bytes, None) => write!(f, "{}", bytes),
+            Cell::Size(bytes, Some(unit)) => {
+                write!(f, "{} {}", unit.round_up(*bytes), unit.label())
+            }
+            Cell::Cost(cost) => f.write_str(&format::cost(*cost)),
+            Cell::Millicents(millicents) => write!(f, "{}", millicents),
+            Cell::Number(Some(number)) => write!(f, "{:.1}", number),
+            Cell::Count(Some(count)) => write!(f, "{}", count),
+            Cell::Number(None) | Cell::Percent(None) | Cell::Count(None) => f.write_str("-"),
+            Cell::Percent(Some(percent)) => write!(f, "{:.1}%", percent),
+        }
+    }
+}
+
+impl Style {
+    /// a cost in dollars, as a cell in the chosen units
+    pub fn cost(
+        &self,
+        dollars: f64,
+    ) -> Cell {
+        match self.units {
+            Units::Dollars => Cell::Cost(dollars),
+            Units::Millicents => Cell::Millicents((dollars * 100_000.0).round() as i64),
+        }
+    }
+}
+
+impl Field {
+    /// `--fields` names, in the order documented by `--help`
+    pub const ALL: &'static [Field] = &[
+        Field::Account,
+        Field::Name,
+        Field::RawName,
+        Field::Arn,
+        Field::LastPushed,
+        Field::AgeDays,
+        Field::Size,
+        Field::SizeDelta,
+        Field::AggregateSize,
+        Field::ArtifactSize,
+        Field::CappedSize,
+        Field::LargestImage,
+        Field::LargestImageSize,
+        Field::MinSize,
+        Field::MedianSize,
+        Field::MaxSize,
+        Field::SizeStddev,
+        Field::ReconciledSize,
+        Field::SizeDiscrepancy,
+        Field::Images,
+        Field::Cost,
+        Field::CappedCost,
+        Field::CostPerImage,
+        Field::CostFormula,
+        Field::Authors,
+        Field::Layers,
+        Field::PercentOfTotal,
+        Field::LatestOnly,
+        Field::Sampled,
+        Field::LatestCost,
+        Field::HistoryCost,
+    ];
+
+    /// columns reported when `--fields` isn't given
+    pub const DEFAULT: &'static [Field] = &[
+        Field::Name,
+        Field::LastPushed,
+        Field::Size,
+        Field::Images,
+        Field::Cost,
+        Field::CappedCost,
+    ];
+
+    /// metrics reported by `--format delta-csv` when `--fields` isn't given
+    pub const METRICS: &'static [Field] = &[
+        Field::Size,
+        Field::AggregateSize,
+        Field::CappedSize,
+        Field::Images,
+        Field::Cost,
+        Field::CappedCost,
+    ];
+
+    /// columns appended to the defaults with `--extra-columns`
+    pub const EXTRA: &'static [Field] = &[
+        Field::Arn,
+        Field::SizeDelta,
+        Field::CostPerImage,
+        Field::LargestImage,
+        Field::LargestImageSize,
+    ];
+
+    /// columns appended with `--cost-breakdown`
+    pub const COST_BREAKDOWN: &'static [Field] = &[Field::LatestCost, Field::HistoryCost];
+
+    /// columns appended with `--stats`
+    pub const STATS: &'static [Field] = &[
+        Field::MinSize,
+        Field::MedianSize,
+        Field::MaxSize,
+        Field::SizeStddev,
+    ];
+
+    /// the default columns, adjusted for accounts and `--extra-columns`
+    pub fn defaults(
+        accounts: bool,
+        extra_columns: bool,
+    ) -> Vec<Field> {
+        let mut fields = Vec::new();
+        if accounts {
+            fields.push(Field::Account);
+        }
+        fields.extend(Self::DEFAULT);
+        if extra_columns {
+            fields.extend(Self::EXTRA);
+        }
+        fields
+    }
+
+    /// short name accepted by `--fields`
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Account => "account",
+            Field::Name => "name",
+            Field::RawName => "raw_name",
+            Field::Arn => "arn",
+            Field::LastPushed => "last_pushed",
+            Field::AgeDays => "age_days",
+            Field::Size => "size",
+            Field::SizeDelta => "size_delta",
+            Field::AggregateSize => "aggregate_size",
+            Field::ArtifactSize => "artifact_size",
+            Field::CappedSize => "capped_size",
+            Field::LargestImage => "largest_image",
+            Field::LargestImageSize => "largest_image_size",
+            Field::MinSize => "min_size",
+            Field::MedianSize => "median_size",
+            Field::MaxSize => "max_size",
+            Field::SizeStddev => "size_stddev",
+            Field::ReconciledSize => "reconciled_size",
+            Field::SizeDiscrepancy => "size_discrepancy",
+            Field::Images => "images",
+            Field::Cost => "cost",
+            Field::CappedCost => "capped_cost",
+            Field::CostPerImage => "cost_per_image",
+            Field::CostFormula => "cost_formula",
+            Field::Authors => "authors",
+            Field::Layers => "layers",
+            Field::PercentOfTotal => "percent_of_total",
+            Field::LatestOnly => "latest_only",
+            Field::Sampled => "sampled",
+            Field::LatestCost => "latest_cost",
+            Field::HistoryCost => "history_cost",
+        }
+    }
+
+    /// whether the field is a cost, reported in `--units`
+    pub fn is_cost(self) -> bool {
+        matches!(
+            self,
+            Field::Cost
+                | Field::CappedCost
+                | Field::CostPerImage
+                | Field::LatestCost
+                | Field::HistoryCost
+        )
+    }
+
+    /// csv header, json key, and sql column, naming the units of costs other
+    /// than dollars
+    pub fn column(
+        self,
+        style: &Style,
+    ) -> String {
+        match (self.is_cost(), style.units) {
+            (true, Units::Millicents) => format!("{}_millicents", self.key()),
+            _ => self.key().to_string(),
+        }
+    }
+
+    /// csv header and json key, matching the fields of the json report
+    pub fn key(self) -> &'static str {
+        match self {
+            Field::Account => "account",
+            Field::Name => "name",
+            Field::RawName => "raw_name",
+            Field::Arn => "arn",
+            Field::LastPushed => "last_pushed_at",
+            Field::AgeDays => "age_days",
+            Field::Size => "latest_image_size",
+            Field::SizeDelta => "size_delta",
+            Field::AggregateSize => "aggregate_image_size",
+            Field::ArtifactSize => "artifact_size",
+            Field::CappedSize => "recent_image_size",
+            Field::LargestImage => "largest_image",
+            Field::LargestImageSize => "largest_image_size",
+            Field::MinSize => "min_image_size",
+            Field::MedianSize => "median_image_size",
+            Field::MaxSize => "max_image_size",
+            Field::SizeStddev => "image_size_stddev",
+            Field::ReconciledSize => "reconciled_image_size",
+            Field::SizeDiscrepancy => "size_discrepancy",
+            Field::Images => "hosted_images",
+            Field::Cost => "monthly_cost",
+            Field::CappedCost => "monthly_capped_cost",
+            Field::CostPerImage => "cost_per_image",
+            Field::CostFormula => "cost_formula",
+            Field::Authors => "authors",
+            Field::Layers => "average_layers",
+            Field::PercentOfTotal => "percent_of_total",
+            Field::LatestOnly => "latest_only",
+            Field::Sampled => "sampled",
+            Field::LatestCost => "latest_cost",
+            Field::HistoryCost => "history_cost",
+        }
+    }
+
+    pub fn value(
+        self,
+        repo: &Repo,
+        style: &Style,
+    ) -> Cell {
+        match self {
+            Field::Account => Cell::Text(repo.account.clone()),
+            Field::Name => Cell::Text(Some(repo.name.clone())),
+            Field::RawName => Cell::Text(Some(
+                repo.raw_name.clone().unwrap_or_else(|| repo.name.clone()),
+            )),
+            Field::Arn => Cell::Text(Some(repo.arn.clone())),
+            Field::LastPushed => Cell::Text(repo.last_pushed_at.clone()),
+            Field::AgeDays => Cell::Count(repo.age_days),
+            Field::Size => Cell::Size(repo.latest_image_size, style.round_to),
+            Field::SizeDelta => Cell::Text(Some(repo.size_delta())),
+            Field::AggregateSize => Cell::Size(repo.aggregate_image_size, style.round_to),
+            Field::ArtifactSize => Cell::Size(repo.artifact_size, style.round_to),
+            Field::CappedSize => Cell::Size(repo.recent_image_size, style.round_to),
+            Field::LargestImage => Cell::Text(repo.largest_image.clone()),
+            Field::LargestImageSize => Cell::Size(repo.largest_image_size, style.round_to),
+            Field::MinSize => Cell::Size(repo.size_stats.min, style.round_to),
+            Field::MedianSize => Cell::Size(repo.size_stats.median, style.round_to),
+            Field::MaxSize => Cell::Size(repo.size_stats.max, style.round_to),
+            Field::SizeStddev => Cell::Number(repo.size_stats.stddev),
+            Field::ReconciledSize => Cell::Size(repo.reconciled_image_size, style.round_to),
+            Field::SizeDiscrepancy => {
+                Cell::Integer(repo.reconciled_image_size - repo.aggregate_image_size)
+            }
+            Field::Images => Cell::Integer(repo.hosted_images as i64),
+            Field::Cost => style.cost(repo.monthly_cost()),
+            Field::CappedCost => style.cost(repo.monthly_capped_cost()),
+            Field::CostPerImage => style.cost(repo.cost_per_image()),
+            Field::LatestCost => style.cost(repo.latest_cost()),
+            Field::HistoryCost => style.cost(repo.history_cost()),
+            Field::CostFormula => Cell::Text(Some(repo.cost_formula())),
+            Field::Authors => Cell::Text(Some(repo.authors.clone().unwrap_or_else(|| "-".into()))),
+            Field::Layers => Cell::Number(repo.average_layers),
+            Field::PercentOfTotal => Cell::Percent(if style.total_cost > 0.0 {
+                Some(repo.monthly_cost() / style.total_cost * 100.0)
+            } else {
+                None
+            }),
+            Field::LatestOnly => {
+                Cell::Text(Some(if repo.latest_only { "yes" } else { "no" }.into()))
+            }
+            Field::Sampled => Cell::Text(Some(if repo.sampled { "yes" } else { "no" }.into())),
+        }
+    }
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|field| field.name() == name)
+            .ok_or_else(|| {
+                format!(
+                    "unknown field {}, expected one of {}",
+                    name,
+                    Self::ALL
+                        .iter()
+                        .map(|field| field.name())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn names_parse_back_to_their_field() {
+        for field in Field::ALL {
+            assert!(field.name().parse::<Field>().unwrap() == *field);
+        }
+        assert!("monthly_cost"
+            .parse::<Field>()
+            .err()
+            .unwrap()
+            .starts_with("unknown field monthly_cost, expected one of account, name, raw_name"));
+    }
+
+    #[test]
+    fn costs_are_suffixed_in_millicents() {
+        let millicents = Style {
+            units: Units::Millicents,
+            ..Style::default()
+        };
+        assert_eq!(Field::Cost.column(&Style::default()), "monthly_cost");
+        assert_eq!(Field::Cost.column(&millicents), "monthly_cost_millicents");
+        assert_eq!(Field::Size.column(&millicents), "latest_image_size");
+        assert!(matches!(millicents.cost(0.3), Cell::Millicents(30_000)));
+        assert!(matches!(millicents.cost(0.000_004), Cell::Millicents(0)));
+        assert!(matches!(millicents.cost(0.000_006), Cell::Millicents(1)));
+        assert!(matches!(Style::default().cost(0.3), Cell::Cost(cost) if cost == 0.3));
+    }
+
+    #[test]
+    fn sizes_round_up_to_whole_units() {
+        assert_eq!(Unit::Kib.round_up(0), 0);
+        assert_eq!(Unit::Kib.round_up(1), 1);
+        assert_eq!(Unit::Kib.round_up(1024), 1);
+        assert_eq!(Unit::Kib.round_up(1025), 2);
+        assert_eq!(Unit::Gib.round_up(3 << 30), 3);
+        assert_eq!(Unit::Tib.round_up(1), 1);
+    }
+
+    #[test]
+    fn units_parse_by_name() {
+        assert!(matches!("MiB".parse(), Ok(Unit::Mib)));
+        assert!(matches!("tib".parse(), Ok(Unit::Tib)));
+        assert_eq!(
+            "mb".parse::<Unit>().err().unwrap(),
+            "unknown unit mb, expected one of kib, mib, gib, or tib"
+        );
+        assert!("millicents".parse::<Units>().unwrap() == Units::Millicents);
+        assert!("cents".parse::<Units>().is_err());
+    }
+
+    #[test]
+    fn cells_display_for_tables() {
+        assert_eq!(Cell::Text(None).to_string(), "");
+        assert_eq!(Cell::Size(2048, None).to_string(), "2048");
+        assert_eq!(Cell::Size(2049, Some(Unit::Kib)).to_string(), "3 KiB");
+        assert_eq!(Cell::Cost(1.234).to_string(), "$1.23");
+        assert_eq!(Cell::Number(Some(2.25)).to_string(), "2.2");
+        assert_eq!(Cell::Percent(Some(12.34)).to_string(), "12.3%");
+        assert_eq!(Cell::Number(None).to_string(), "-");
+        assert_eq!(Cell::Percent(None).to_string(), "-");
+        assert_eq!(Cell::Count(None).to_string(), "-");
+    }
+
+    #[test]
+    fn cells_serialize_unavailable_values_as_null() {
+        assert_eq!(Cell::Text(None).to_json(), Value::Null);
+        assert_eq!(Cell::Number(None).to_json(), Value::Null);
+        assert_eq!(Cell::Count(None).to_json(), Value::Null);
+        assert_eq!(Cell::Text(Some("app".into())).to_json(), json!("app"));
+        assert_eq!(Cell::Size(2049, Some(Unit::Kib)).to_json(), json!(3));
+        assert_eq!(Cell::Millicents(12).to_json(), json!(12));
+    }
+
+    #[test]
+    fn defaults_add_accounts_and_extra_columns() {
+        assert!(Field::defaults(false, false) == Field::DEFAULT);
+        let fields = Field::defaults(true, true);
+        assert!(fields[0] == Field::Account);
+        assert!(fields[1..=Field::DEFAULT.len()] == *Field::DEFAULT);
+        assert!(fields[Field::DEFAULT.len() + 1..] == *Field::EXTRA);
+    }
+
+    #[test]
+    fn shares_of_the_total_need_a_total() {
+        let repo = crate::tests::repo("app");
+        assert!(matches!(
+            Field::PercentOfTotal.value(&repo, &Style::default()),
+            Cell::Percent(None)
+        ));
+        let style = Style {
+            total_cost: repo.monthly_cost() * 4.0,
+            ..Style::default()
+        };
+        assert!(matches!(
+            Field::PercentOfTotal.value(&repo, &style),
+            Cell::Percent(Some(percent)) if (percent - 25.0).abs() < 1e-9
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+/// binary units sizes are shown in, largest first
+const UNITS: &[(i64, &str)] = &[
+    (1 << 60, "EiB"),
+    (1 << 50, "PiB"),
+    (1 << 40, "TiB"),
+    (1 << 30, "GiB"),
+    (1 << 20, "MiB"),
+    (1 << 10, "KiB"),
+];
+
+/// a size for humans in the largest binary unit it spans, e.g. `1.5 GiB`, or
+/// plain bytes below a KiB. negative sizes (shrinking deltas) keep their sign
+pub fn bytes(bytes: i64) -> String {
+    let magnitude = bytes.unsigned_abs();
+    let sign = if bytes < 0 { "-" } else { "" };
+    UNITS
+        .iter()
+        .find(|(size, _)| magnitude >= *size as u64)
+        .map(|(size, unit)| format!("{}{:.1} {}", sign, magnitude as f64 / *size as f64, unit))
+        .unwrap_or_else(|| format!("{}{} B", sign, magnitude))
+}
+
+/// dollars to the cent, e.g. `$1.23`, the sign ahead of the currency symbol.
+/// costs which round to zero cents are shown unsigned rather than as `-$0.00`
+pub fn cost(dollars: f64) -> String {
+    let cents = (dollars * 100.0).round();
+    if cents < 0.0 {
+        format!("-${:.2}", -cents / 100.0)
+    } else {
+        format!("${:.2}", cents.abs() / 100.0)
+    }
+}
+
+/// an age in whole days for humans, in the largest unit it spans
+pub fn humanize_days(days: i64) -> String {
+    let (count, unit) = match days {
+        days if days < 1 => return "today".into(),
+        1 => return "yesterday".into(),
+        days if days < 30 => (days, "day"),
+        days if days < 365 => (days / 30, "month"),
+        days => (days / 365, "year"),
+    };
+    format!(
+        "{} {}{} ago",
+        count,
+        unit,
+        if count == 1 { "" } else { "s" }
+    )
+}
+
+/// a name cut to `width` characters, rather than bytes, so multibyte names
+/// aren't split mid character, its last one replaced by an ellipsis when cut
+pub fn truncate(
+    name: &str,
+    width: usize,
+) -> String {
+    if name.chars().count() <= width {
+        name.to_string()
+    } else {
+        name.chars()
+            .take(width.saturating_sub(1))
+            .chain(Some('…').filter(|_| width > 0))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_below_a_kib_are_plain() {
+        assert_eq!(bytes(0), "0 B");
+        assert_eq!(bytes(1023), "1023 B");
+    }
+
+    #[test]
+    fn bytes_use_the_largest_unit_spanned() {
+        assert_eq!(bytes(1 << 10), "1.0 KiB");
+        assert_eq!(bytes(1 << 30), "1.0 GiB");
+        assert_eq!(bytes((1 << 30) - 1), "1024.0 MiB");
+        assert_eq!(bytes(3 << 39), "1.5 TiB");
+    }
+
+    #[test]
+    fn bytes_handle_the_extremes() {
+        assert_eq!(bytes(i64::MAX), "8.0 EiB");
+        assert_eq!(bytes(i64::MIN), "-8.0 EiB");
+    }
+
+    #[test]
+    fn negative_bytes_keep_their_sign() {
+        assert_eq!(bytes(-1536), "-1.5 KiB");
+        assert_eq!(bytes(-1), "-1 B");
+    }
+
+    #[test]
+    fn costs_are_rounded_to_the_cent() {
+        assert_eq!(cost(0.0), "$0.00");
+        assert_eq!(cost(1.234), "$1.23");
+        assert_eq!(cost(1.236), "$1.24");
+        assert_eq!(cost(-1.5), "-$1.50");
+    }
+
+    #[test]
+    fn costs_rounding_to_zero_are_unsigned() {
+        assert_eq!(cost(-0.004), "$0.00");
+        assert_eq!(cost(-0.0), "$0.00");
+        assert_eq!(cost(-0.005), "-$0.01");
+    }
+
+    #[test]
+    fn humanize_days_boundaries() {
+        assert_eq!(humanize_days(-1), "today");
+        assert_eq!(humanize_days(0), "today");
+        assert_eq!(humanize_days(1), "yesterday");
+        assert_eq!(humanize_days(2), "2 days ago");
+        assert_eq!(humanize_days(29), "29 days ago");
+        assert_eq!(humanize_days(30), "1 month ago");
+        assert_eq!(humanize_days(364), "12 months ago");
+        assert_eq!(humanize_days(365), "1 year ago");
+        assert_eq!(humanize_days(730), "2 years ago");
+    }
+
+    #[test]
+    fn truncate_keeps_names_that_fit() {
+        assert_eq!(truncate("service", 7), "service");
+        assert_eq!(truncate("", 0), "");
+    }
+
+    #[test]
+    fn truncate_ends_cut_names_with_an_ellipsis() {
+        assert_eq!(truncate("service", 4), "ser…");
+        assert_eq!(truncate("service", 1), "…");
+        assert_eq!(truncate("service", 0), "");
+    }
+
+    #[test]
+    fn truncate_counts_characters_not_bytes() {
+        assert_eq!(truncate("héllo", 5), "héllo");
+        assert_eq!(truncate("héllo", 3), "hé…");
+    }
+}
@@ -0,0 +1,84 @@
+use serde_json::Value;
+use std::io::{Result, Write};
+
+/// writes a json value as gron style assignments, one per line, so that
+/// individual fields can be found with grep, e.g.
+/// `json.repositories[0].name = "x";`
+pub fn write(
+    writer: &mut impl Write,
+    path: &str,
+    value: &Value,
+) -> Result<()> {
+    match value {
+        Value::Object(fields) => {
+            writeln!(writer, "{} = {{}};", path)?;
+            for (key, value) in fields {
+                write(writer, &format!("{}{}", path, accessor(key)), value)?;
+            }
+            Ok(())
+        }
+        Value::Array(values) => {
+            writeln!(writer, "{} = [];", path)?;
+            for (index, value) in values.iter().enumerate() {
+                write(writer, &format!("{}[{}]", path, index), value)?;
+            }
+            Ok(())
+        }
+        scalar => writeln!(writer, "{} = {};", path, scalar),
+    }
+}
+
+/// `.key` for keys that are valid identifiers, `["key"]` otherwise
+fn accessor(key: &str) -> String {
+    let mut chars = key.chars();
+    let identifier = chars
+        .next()
+        .map(|first| first.is_ascii_alphabetic() || first == '_' || first == '$')
+        .unwrap_or_default()
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if identifier {
+        format!(".{}", key)
+    } else {
+        format!("[{}]", Value::String(key.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn gron(value: Value) -> String {
+        let mut out = Vec::new();
+        write(&mut out, "json", &value).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn scalars_are_single_assignments() {
+        assert_eq!(gron(json!(1)), "json = 1;\n");
+        assert_eq!(gron(json!("a \"b\"")), "json = \"a \\\"b\\\"\";\n");
+        assert_eq!(gron(Value::Null), "json = null;\n");
+    }
+
+    #[test]
+    fn objects_and_arrays_are_declared_before_their_members() {
+        assert_eq!(
+            gron(json!({"repositories": [{"name": "x"}]})),
+            "json = {};\n\
+             json.repositories = [];\n\
+             json.repositories[0] = {};\n\
+             json.repositories[0].name = \"x\";\n"
+        );
+    }
+
+    #[test]
+    fn keys_which_arent_identifiers_are_bracketed() {
+        assert_eq!(accessor("name"), ".name");
+        assert_eq!(accessor("_$id2"), "._$id2");
+        assert_eq!(accessor("2x"), "[\"2x\"]");
+        assert_eq!(accessor("team/api"), "[\"team/api\"]");
+        assert_eq!(accessor("say \"hi\""), "[\"say \\\"hi\\\"\"]");
+        assert_eq!(accessor(""), "[\"\"]");
+    }
+}
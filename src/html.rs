@@ -0,0 +1,226 @@
+use crate::{
+    fields::{Cell, Field, Style},
+    format, Repo, Totals,
+};
+use std::io::{Result, Write};
+
+/// width of the chart's name column and of its longest bar, in pixels
+const LABEL_WIDTH: usize = 240;
+const BAR_WIDTH: usize = 480;
+const BAR_HEIGHT: usize = 20;
+
+/// escapes text for element content and double quoted attributes
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// numbers are right aligned so their digits line up
+fn numeric(cell: &Cell) -> bool {
+    !matches!(cell, Cell::Text(_))
+}
+
+/// writes a standalone html page with a table of one row per repository and a
+/// footer row of total costs, preceded with `chart_top` by an inline svg bar
+/// chart of the costliest repositories
+pub fn write(
+    writer: &mut impl Write,
+    fields: &[Field],
+    style: &Style,
+    repos: &[Repo],
+    totals: &Totals,
+    chart_top: Option<usize>,
+) -> Result<()> {
+    writeln!(writer, "<!DOCTYPE html>")?;
+    writeln!(writer, "<html>")?;
+    writeln!(writer, "<head>")?;
+    writeln!(writer, "<meta charset=\"utf-8\">")?;
+    writeln!(writer, "<title>ecr-insights</title>")?;
+    writeln!(
+        writer,
+        "<style>table {{ border-collapse: collapse; }} th, td {{ padding: 2px 8px; }} \
+         .number {{ text-align: right; }}</style>"
+    )?;
+    writeln!(writer, "</head>")?;
+    writeln!(writer, "<body>")?;
+    if let Some(top) = chart_top {
+        chart(writer, repos, top)?;
+    }
+    writeln!(writer, "<table>")?;
+    writeln!(writer, "<thead>")?;
+    writeln!(
+        writer,
+        "<tr>{}</tr>",
+        fields
+            .iter()
+            .map(|field| format!("<th>{}</th>", escape(&field.column(style))))
+            .collect::<String>()
+    )?;
+    writeln!(writer, "</thead>")?;
+    writeln!(writer, "<tbody>")?;
+    for repo in repos {
+        writeln!(
+            writer,
+            "<tr>{}</tr>",
+            fields
+                .iter()
+                .map(|field| {
+                    let cell = field.value(repo, style);
+                    format!(
+                        "<td{}>{}</td>",
+                        if numeric(&cell) {
+                            " class=\"number\""
+                        } else {
+                            ""
+                        },
+                        escape(&cell.to_string())
+                    )
+                })
+                .collect::<String>()
+        )?;
+    }
+    writeln!(writer, "</tbody>")?;
+    writeln!(writer, "<tfoot>")?;
+    writeln!(
+        writer,
+        "<tr>{}</tr>",
+        fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| match field {
+                Field::Cost => format!(
+                    "<td class=\"number\">{}</td>",
+                    style.cost(totals.monthly_cost)
+                ),
+                Field::CappedCost => format!(
+                    "<td class=\"number\">{}</td>",
+                    style.cost(totals.monthly_capped_cost)
+                ),
+                _ if index == 0 => "<td>total</td>".into(),
+                _ => "<td></td>".into(),
+            })
+            .collect::<String>()
+    )?;
+    writeln!(writer, "</tfoot>")?;
+    writeln!(writer, "</table>")?;
+    writeln!(writer, "</body>")?;
+    writeln!(writer, "</html>")
+}
+
+/// horizontal bars of the `top` costliest repositories' monthly costs, scaled
+/// to the costliest, each labeled with its name and cost
+fn chart(
+    writer: &mut impl Write,
+    repos: &[Repo],
+    top: usize,
+) -> Result<()> {
+    let mut charted = repos.iter().collect::<Vec<_>>();
+    charted.sort_by(|a, b| b.monthly_cost().total_cmp(&a.monthly_cost()));
+    charted.truncate(top);
+    let max = charted
+        .first()
+        .map(|repo| repo.monthly_cost())
+        .unwrap_or_default();
+    // room after the longest bar for its cost
+    let width = LABEL_WIDTH + BAR_WIDTH + 80;
+    writeln!(
+        writer,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+         font-family=\"sans-serif\" font-size=\"12\">",
+        width,
+        charted.len() * BAR_HEIGHT
+    )?;
+    for (index, repo) in charted.iter().enumerate() {
+        let y = index * BAR_HEIGHT;
+        let cost = repo.monthly_cost();
+        let bar = if max > 0.0 {
+            (cost / max * BAR_WIDTH as f64).round() as usize
+        } else {
+            0
+        };
+        writeln!(
+            writer,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+            LABEL_WIDTH - 6,
+            y + 14,
+            escape(&repo.name)
+        )?;
+        writeln!(
+            writer,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#4878a8\"/>",
+            LABEL_WIDTH,
+            y + 2,
+            bar,
+            BAR_HEIGHT - 4
+        )?;
+        writeln!(
+            writer,
+            "<text x=\"{}\" y=\"{}\">{}</text>",
+            LABEL_WIDTH + bar + 6,
+            y + 14,
+            format::cost(cost)
+        )?;
+    }
+    writeln!(writer, "</svg>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markup_characters_are_escaped() {
+        assert_eq!(escape("team/api"), "team/api");
+        assert_eq!(
+            escape("<a href=\"x\">&</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+        );
+        assert_eq!(escape("&lt;"), "&amp;lt;");
+    }
+
+    #[test]
+    fn writes_escaped_rows_and_a_total_footer() {
+        let repo = crate::tests::repo("<script>");
+        let totals = Totals::default().add(&repo);
+        let mut out = Vec::new();
+        write(
+            &mut out,
+            &[Field::Name, Field::Images, Field::Cost],
+            &Style::default(),
+            &[repo],
+            &totals,
+            None,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(
+            "<tr><td>&lt;script&gt;</td><td class=\"number\">2</td>\
+             <td class=\"number\">$0.30</td></tr>"
+        ));
+        assert!(out.contains("<tr><td>total</td><td></td><td class=\"number\">$0.30</td></tr>"));
+        assert!(!out.contains("<svg"));
+    }
+
+    #[test]
+    fn charts_the_costliest_repositories_first() {
+        let mut cheap = crate::tests::repo("cheap");
+        cheap.billed_image_size /= 2;
+        let repos = [
+            cheap,
+            crate::tests::repo("costly"),
+            crate::tests::repo("other"),
+        ];
+        let mut out = Vec::new();
+        chart(&mut out, &repos, 2).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let labels = out
+            .lines()
+            .filter(|line| line.contains("text-anchor=\"end\""))
+            .collect::<Vec<_>>();
+        assert_eq!(labels.len(), 2);
+        assert!(labels[0].ends_with(">costly</text>"));
+        assert!(out.contains(&format!("width=\"{}\" height=\"16\"", BAR_WIDTH)));
+    }
+}
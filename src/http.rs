@@ -0,0 +1,94 @@
+use hyper::{client::HttpConnector, Uri};
+use hyper_proxy::{Proxy, ProxyConnector};
+use hyper_tls::HttpsConnector;
+use native_tls::TlsConnector;
+use rusoto_core::HttpClient;
+use std::{env, error::Error, time::Duration};
+
+/// consulted, in order, for a proxy when `--proxy` isn't given
+const PROXY_VARIABLES: &[&str] = &["HTTPS_PROXY", "https_proxy"];
+
+/// consulted, in order, for comma separated hosts to reach directly
+const NO_PROXY_VARIABLES: &[&str] = &["NO_PROXY", "no_proxy"];
+
+/// fail unreachable endpoints (or proxies) promptly rather than hanging
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub type Connector = ProxyConnector<HttpsConnector<HttpConnector>>;
+
+fn variable(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// whether a host matches `NO_PROXY`, either exactly, as a subdomain of an
+/// entry, or because an entry is `*`
+fn bypassed(
+    no_proxy: &[String],
+    host: Option<&str>,
+) -> bool {
+    let host = host.unwrap_or_default();
+    no_proxy.iter().any(|entry| {
+        let entry = entry.trim_start_matches('.');
+        entry == "*" || host == entry || host.ends_with(&format!(".{}", entry))
+    })
+}
+
+/// an http client for aws clients, tunneling through `proxy`, or the one
+/// named by `HTTPS_PROXY`, for hosts not listed in `NO_PROXY`
+pub fn client(proxy: Option<&str>) -> Result<HttpClient<Connector>, Box<dyn Error>> {
+    let mut http = HttpConnector::new(4);
+    http.enforce_http(false);
+    http.set_connect_timeout(Some(CONNECT_TIMEOUT));
+    let https = HttpsConnector::from((http, TlsConnector::new()?));
+    let connector = match proxy
+        .map(String::from)
+        .or_else(|| variable(PROXY_VARIABLES))
+    {
+        Some(proxy) => {
+            let uri = proxy
+                .parse::<Uri>()
+                .map_err(|err| format!("invalid proxy {}: {}", proxy, err))?;
+            let no_proxy = variable(NO_PROXY_VARIABLES)
+                .unwrap_or_default()
+                .split(',')
+                .map(|entry| entry.trim().to_string())
+                .filter(|entry| !entry.is_empty())
+                .collect::<Vec<_>>();
+            let intercept = move |_: Option<&str>, host: Option<&str>, _: Option<u16>| {
+                !bypassed(&no_proxy, host)
+            };
+            ProxyConnector::from_proxy(https, Proxy::new(intercept, uri))?
+        }
+        None => ProxyConnector::unsecured(https),
+    };
+    Ok(HttpClient::from_connector(connector))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_proxy(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(|entry| entry.to_string()).collect()
+    }
+
+    #[test]
+    fn hosts_are_bypassed_exactly_or_as_subdomains() {
+        let entries = no_proxy(&["amazonaws.com", ".internal"]);
+        assert!(bypassed(&entries, Some("amazonaws.com")));
+        assert!(bypassed(&entries, Some("api.ecr.us-east-1.amazonaws.com")));
+        assert!(bypassed(&entries, Some("registry.internal")));
+        assert!(!bypassed(&entries, Some("notamazonaws.com")));
+        assert!(!bypassed(&entries, None));
+        assert!(!bypassed(&[], Some("amazonaws.com")));
+    }
+
+    #[test]
+    fn a_wildcard_bypasses_every_host() {
+        assert!(bypassed(&no_proxy(&["*"]), Some("example.com")));
+        assert!(bypassed(&no_proxy(&["*"]), None));
+    }
+}
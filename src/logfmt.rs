@@ -0,0 +1,119 @@
+use crate::{
+    fields::{Field, Style},
+    Repo, Totals,
+};
+use serde_json::Value;
+use std::io::{Result, Write};
+
+/// a value quoted when it holds spaces, quotes, or `=`, which would otherwise
+/// split it into more pairs
+fn value(value: Value) -> String {
+    let text = match value {
+        Value::Null => return String::new(),
+        Value::String(text) => text,
+        other => return other.to_string(),
+    };
+    if text.is_empty() || text.contains(|c: char| c.is_whitespace() || c == '"' || c == '=') {
+        format!("{:?}", text)
+    } else {
+        text
+    }
+}
+
+fn pairs(pairs: impl IntoIterator<Item = (String, Value)>) -> String {
+    pairs
+        .into_iter()
+        .map(|(key, pair)| format!("{}={}", key, value(pair)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// writes a line of `key=value` pairs per repository, keyed by field, then a
+/// line of totals marked `totals=true`
+pub fn write(
+    writer: &mut impl Write,
+    fields: &[Field],
+    style: &Style,
+    repos: &[Repo],
+    totals: &Totals,
+) -> Result<()> {
+    for repo in repos {
+        writeln!(
+            writer,
+            "{}",
+            pairs(
+                fields
+                    .iter()
+                    .map(|field| (field.column(style), field.value(repo, style).to_json()))
+            )
+        )?;
+    }
+    writeln!(
+        writer,
+        "{}",
+        pairs(vec![
+            ("totals".into(), true.into()),
+            ("repositories".into(), totals.repositories.into()),
+            ("hosted_images".into(), totals.hosted_images.into()),
+            (
+                Field::Cost.column(style),
+                style.cost(totals.monthly_cost).to_json()
+            ),
+            (
+                Field::CappedCost.column(style),
+                style.cost(totals.monthly_capped_cost).to_json()
+            ),
+        ])
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fields::Units;
+
+    #[test]
+    fn plain_values_are_bare() {
+        assert_eq!(value("team/api".into()), "team/api");
+        assert_eq!(value(3.into()), "3");
+        assert_eq!(value(true.into()), "true");
+        assert_eq!(value(Value::Null), "");
+    }
+
+    #[test]
+    fn values_which_would_split_are_quoted() {
+        assert_eq!(value("".into()), "\"\"");
+        assert_eq!(value("two words".into()), "\"two words\"");
+        assert_eq!(value("a=b".into()), "\"a=b\"");
+        assert_eq!(value("say \"hi\"".into()), "\"say \\\"hi\\\"\"");
+        assert_eq!(value("tab\there".into()), "\"tab\\there\"");
+    }
+
+    #[test]
+    fn writes_a_line_per_repository_then_totals() {
+        let repo = crate::tests::repo("team api");
+        let totals = Totals::default().add(&repo);
+        let style = Style {
+            units: Units::Millicents,
+            ..Style::default()
+        };
+        let mut out = Vec::new();
+        write(
+            &mut out,
+            &[Field::Name, Field::Images],
+            &style,
+            &[repo],
+            &totals,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "name=\"team api\" hosted_images=2");
+        assert!(
+            lines[1]
+                .starts_with("totals=true repositories=1 hosted_images=2 monthly_cost_millicents="),
+            "{}",
+            lines[1]
+        );
+    }
+}
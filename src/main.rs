@@ -1,22 +1,249 @@
-use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Utc};
-use rusoto_core::Region;
+mod asciidoc;
+mod clipboard;
+mod cost_model;
+mod fields;
+mod format;
+mod gron;
+mod html;
+mod http;
+mod logfmt;
+mod manifest;
+mod openmetrics;
+mod parquet_file;
+mod protobuf;
+mod sql;
+mod template;
+mod toml_file;
+mod tree;
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use cost_model::Model;
+use fields::{Cell, Field, Style, Unit, Units};
+use log::debug;
+use manifest::Manifest;
+use rusoto_core::{
+    credential::{
+        CredentialsError, DefaultCredentialsProvider, ProfileProvider, ProvideAwsCredentials,
+    },
+    Region, RusotoError, RusotoFuture,
+};
 use rusoto_ecr::{
-    DescribeImagesRequest, DescribeRepositoriesRequest, Ecr, EcrClient, ImageDetail, Repository,
+    BatchGetImageError, DescribeImagesError, DescribeImagesFilter, DescribeImagesRequest,
+    DescribeRepositoriesError, DescribeRepositoriesRequest, Ecr, EcrClient, ImageDetail,
+    ImageIdentifier, Repository,
 };
+use rusoto_iam::{Iam, IamClient, ListAccountAliasesRequest};
+use rusoto_sts::{
+    GetCallerIdentityError, GetCallerIdentityRequest, GetCallerIdentityResponse, Sts, StsClient,
+};
+use rust_xlsxwriter::{cell_range, Format, Workbook};
+use serde::Serialize;
+use serde_json::Value;
 use std::{
+    cmp::{self, Reverse},
+    collections::{BinaryHeap, HashMap, HashSet},
+    env,
     error::Error,
-    io::{stdout, Error as IoError, Write},
+    fmt, fs,
+    io::{
+        stderr, stdout, BufWriter, Error as IoError, ErrorKind as IoErrorKind, IsTerminal, Write,
+    },
+    mem, panic,
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering},
+        mpsc::{self, SyncSender},
+        Mutex, PoisonError,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use structopt::{
+    clap::{ArgGroup, ErrorKind},
+    StructOpt,
 };
-use structopt::StructOpt;
 use tabwriter::TabWriter;
+use template::Template;
 
 struct Repo {
+    /// aws profile the repository was found under when scanning `--profiles`
+    account: Option<String>,
     name: String,
+    /// name as found in the registry, when changed by `--normalize-names`
+    raw_name: Option<String>,
+    arn: String,
     last_pushed_at: Option<String>,
+    /// whole days from the last push to now, or `--as-of`
+    age_days: Option<i64>,
     latest_image_size: i64,
+    /// size of the image pushed before the latest one, if any
+    previous_image_size: Option<i64>,
+    /// first tag, or digest when untagged, of the biggest image
+    largest_image: Option<String>,
+    largest_image_size: i64,
+    /// distribution of hosted image sizes
+    size_stats: SizeStats,
+    /// aggregate size counting manifest lists as the sum of their platform
+    /// images, populated with `--reconcile`
+    reconciled_image_size: i64,
     aggregate_image_size: i64,
+    /// blob size of the oci artifacts hosted, populated with `--artifacts`
+    artifact_size: i64,
     recent_image_size: i64,
+    /// size of the untagged images and those beyond the cap, which deleting
+    /// would reclaim
+    reclaimable_image_size: i64,
+    /// bytes billed for the month under the `--cost-model`
+    billed_image_size: i64,
+    /// images hosted at the start of the month. ecr lists each digest once,
+    /// along with all of its tags, so this also counts distinct digests
     hosted_images: usize,
+    /// every image in the repository, including those pushed this month
+    total_images: usize,
+    pricing: Pricing,
+    /// images without any tags, including those pushed this month
+    untagged_images: usize,
+    /// every tag ever kept is `latest`, overwritten on each push
+    latest_only: bool,
+    /// more images were hosted than `--sample` counted
+    sampled: bool,
+    /// populated with `--tag-costs`
+    tag_costs: Vec<TagCost>,
+    /// authors annotation of the latest image, when its manifest carries one
+    authors: Option<String>,
+    /// mean layers of the images whose manifests could be fetched, populated
+    /// when selected with `--fields`
+    average_layers: Option<f64>,
+    /// populated with `--multi-arch`
+    multi_arch: MultiArch,
+    /// images and size by tag prefix, populated with `--aggregate-by-tag-prefix`
+    tag_prefixes: HashMap<String, TagPrefix>,
+    /// images and size by platform architecture, populated with `--by-architecture`
+    architectures: HashMap<String, TagPrefix>,
+    /// hosted images, most recent first, populated with `--output-per-repo`
+    images: Vec<Image>,
+    /// retained size under each of the `--compare-cap` caps
+    compared_caps: Vec<i64>,
+    /// manifest list entries whose child image is gone, populated with
+    /// `--check-references`
+    broken_references: Vec<BrokenReference>,
+}
+
+/// a manifest list referencing a child manifest the repository no longer hosts
+struct BrokenReference {
+    list: String,
+    child: String,
+}
+
+/// one hosted image, for per repository detail
+struct Image {
+    tags: Vec<String>,
+    digest: String,
+    size: i64,
+    pushed_at: NaiveDateTime,
+}
+
+/// images sharing a tag prefix, e.g. `service` for `service-v1.2.3`
+#[derive(Default)]
+struct TagPrefix {
+    images: usize,
+    size: i64,
+}
+
+impl TagPrefix {
+    /// bucket for images without tags
+    const UNTAGGED: &'static str = "(untagged)";
+
+    /// bucket for images whose architecture can't be determined
+    const UNKNOWN_ARCHITECTURE: &'static str = "(unknown)";
+
+    fn add(
+        self,
+        other: &TagPrefix,
+    ) -> Self {
+        TagPrefix {
+            images: self.images + other.images,
+            size: self.size + other.size,
+        }
+    }
+
+    /// the text before the last `separator` of an image's first tag which has
+    /// one, so `latest` doesn't shadow `service-v1.2.3`, otherwise its first tag
+    fn of<'a>(
+        details: &'a ImageDetail,
+        separator: &str,
+    ) -> &'a str {
+        let tags = details.image_tags.as_deref().unwrap_or_default();
+        tags.iter()
+            .find_map(|tag| tag.rsplit_once(separator).map(|(prefix, _)| prefix))
+            .or_else(|| tags.first().map(String::as_str))
+            .unwrap_or(Self::UNTAGGED)
+    }
+}
+
+/// spread of a repository's image sizes, zero and without a deviation when
+/// it hosts no images
+#[derive(Default)]
+struct SizeStats {
+    min: i64,
+    median: i64,
+    max: i64,
+    stddev: Option<f64>,
+}
+
+impl SizeStats {
+    fn of(mut sizes: Vec<i64>) -> Self {
+        if sizes.is_empty() {
+            return SizeStats::default();
+        }
+        sizes.sort_unstable();
+        let count = sizes.len();
+        let mean = sizes.iter().sum::<i64>() as f64 / count as f64;
+        let variance = sizes
+            .iter()
+            .map(|size| (*size as f64 - mean).powi(2))
+            .sum::<f64>()
+            / count as f64;
+        SizeStats {
+            min: sizes[0],
+            median: if count.is_multiple_of(2) {
+                (sizes[count / 2 - 1] + sizes[count / 2]) / 2
+            } else {
+                sizes[count / 2]
+            },
+            max: sizes[count - 1],
+            stddev: Some(variance.sqrt()),
+        }
+    }
+}
+
+/// manifest lists (multi-arch images) hosted by a repository
+#[derive(Default)]
+struct MultiArch {
+    images: usize,
+    /// reported size of the per platform images the lists reference
+    size: i64,
+    /// most child manifests referenced by any one list
+    platforms: usize,
+}
+
+/// storage apportioned to one image of a repository, shared layers being
+/// split evenly between the images referencing them
+struct TagCost {
+    /// the image's tags, or its digest when untagged
+    tags: String,
+    size: f64,
+}
+
+impl TagCost {
+    fn monthly_cost(
+        &self,
+        pricing: Pricing,
+    ) -> f64 {
+        pricing.cost(self.size as i64)
+    }
 }
 
 impl Repo {
@@ -24,178 +251,5089 @@ impl Repo {
     /// compresses which seems to be what cost reflects
     /// this is not an exact science
     const COMPRESSION: f64 = 0.65;
-    /// Storage is $0.10 per GB-month
-    /// https://aws.amazon.com/ecr/pricing/
+    /// compression ratios reported by `--sensitivity`
+    const SENSITIVITY: [f64; 4] = [0.5, 0.65, 0.8, 1.0];
+
     fn monthly_cost(&self) -> f64 {
-        (self.aggregate_image_size as f64 * Self::COMPRESSION / (1024 * 1024 * 1024) as f64) * 0.10
+        self.pricing.cost(self.billed_image_size)
     }
 
-    /// Storage is $0.10 per GB-month
-    /// https://aws.amazon.com/ecr/pricing/
     fn monthly_capped_cost(&self) -> f64 {
-        (self.recent_image_size as f64 * Self::COMPRESSION / (1024 * 1024 * 1024) as f64) * 0.10
+        self.pricing.cost(self.recent_image_size)
+    }
+
+    /// monthly cost of storing just the latest image
+    fn latest_cost(&self) -> f64 {
+        self.pricing.cost(self.latest_image_size)
+    }
+
+    /// monthly cost of storing every image but the latest, what a retention
+    /// policy could save
+    fn history_cost(&self) -> f64 {
+        self.pricing
+            .cost((self.aggregate_image_size - self.latest_image_size).max(0))
+    }
+
+    /// monthly cost of an average hosted image, zero without any
+    fn cost_per_image(&self) -> f64 {
+        if self.hosted_images == 0 {
+            0.0
+        } else {
+            self.monthly_cost() / self.hosted_images as f64
+        }
+    }
+
+    /// the arithmetic behind `monthly_cost`, for `--explain`
+    fn cost_formula(&self) -> String {
+        format!(
+            "{} B x {} / {} B/{} x {} = {}",
+            self.billed_image_size,
+            self.pricing.compression,
+            self.pricing.bytes_per_gb(),
+            self.pricing.unit(),
+            format::cost(RATE),
+            format::cost(self.monthly_cost())
+        )
+    }
+
+    /// growth of the latest image over the one before it, a dash when there's
+    /// nothing to compare against
+    fn size_delta(&self) -> String {
+        self.previous_image_size
+            .map(|previous| format!("{:+}", self.latest_image_size - previous))
+            .unwrap_or_else(|| "-".into())
+    }
+}
+
+/// Storage is $0.10 per GB-month
+/// https://aws.amazon.com/ecr/pricing/
+const RATE: f64 = 0.10;
+
+/// how stored bytes are converted into billed GB-months
+#[derive(Clone, Copy)]
+struct Pricing {
+    /// 1024 for GiB (2^30 bytes), 1000 for decimal GB (10^9 bytes)
+    gb_base: i64,
+    /// ratio of stored to reported bytes, `Repo::COMPRESSION` by default
+    compression: f64,
+}
+
+impl Pricing {
+    fn bytes_per_gb(self) -> i64 {
+        self.gb_base.pow(3)
+    }
+
+    fn unit(self) -> &'static str {
+        if self.gb_base == 1024 {
+            "GiB"
+        } else {
+            "GB"
+        }
+    }
+
+    fn storage_cost(
+        self,
+        bytes: i64,
+        compression: f64,
+    ) -> f64 {
+        (bytes as f64 * compression / self.bytes_per_gb() as f64) * RATE
+    }
+
+    fn cost(
+        self,
+        bytes: i64,
+    ) -> f64 {
+        self.storage_cost(bytes, self.compression)
+    }
+}
+
+/// a number of bytes, optionally suffixed with a decimal (kb, mb, gb, tb) or
+/// binary (kib, mib, gib, tib) unit, e.g. `1GiB` or `500 MB`
+fn parse_size(size: &str) -> Result<i64, String> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(size.len());
+    let (number, unit) = (&size[..split], size[split..].trim());
+    let number = number
+        .parse::<f64>()
+        .map_err(|_| format!("invalid size {}, expected e.g. 1024, 500MB, or 1GiB", size))?;
+    let multiplier: i64 = match &unit.to_lowercase()[..] {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000_i64.pow(2),
+        "gb" => 1000_i64.pow(3),
+        "tb" => 1000_i64.pow(4),
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        other => {
+            return Err(format!(
+                "unknown size unit {}, expected one of b, kb, mb, gb, tb, kib, mib, gib, or tib",
+                other
+            ))
+        }
+    };
+    Ok((number * multiplier as f64).round() as i64)
+}
+
+/// a day of the month every month has, 1 to 28
+fn parse_period_start_day(day: &str) -> Result<u32, String> {
+    match day.parse() {
+        Ok(day @ 1..=28) => Ok(day),
+        _ => Err(format!(
+            "--period-start-day must be a day from 1 to 28, not {}",
+            day
+        )),
+    }
+}
+
+/// the start of the billing period `date` falls in, on `day` of its month, or
+/// of the month before when `date` is earlier in the month than that
+fn period_start(
+    date: NaiveDate,
+    day: u32,
+) -> NaiveDate {
+    let month = if date.day() >= day {
+        date
+    } else {
+        date.with_day(1)
+            .and_then(|first| first.pred_opt())
+            .unwrap_or(date)
+    };
+    month.with_day(day).unwrap_or(month)
+}
+
+/// a utc date, optionally with a time, e.g. `2024-03-01` or `2024-03-01T12:00:00`
+fn parse_as_of(as_of: &str) -> Result<NaiveDateTime, String> {
+    DateTime::parse_from_rfc3339(as_of)
+        .map(|as_of| as_of.naive_utc())
+        .or_else(|_| NaiveDateTime::parse_from_str(as_of, "%Y-%m-%dT%H:%M:%S"))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(as_of, "%Y-%m-%d")
+                .map(|date| NaiveDateTime::new(date, NaiveTime::MIN))
+        })
+        .map_err(|_| {
+            format!(
+                "invalid date {}, expected e.g. 2024-03-01 or 2024-03-01T12:00:00",
+                as_of
+            )
+        })
+}
+
+fn parse_gb_base(gb_base: &str) -> Result<i64, String> {
+    match gb_base {
+        "1000" => Ok(1000),
+        "1024" => Ok(1024),
+        other => Err(format!("--gb-base must be 1000 or 1024, not {}", other)),
+    }
+}
+
+/// exit statuses, so automation can tell a broken run from a finding
+const EXIT_ERROR: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_BUDGET: i32 = 3;
+const EXIT_POLICY: i32 = 4;
+
+/// an invalid argument or output format combination, exiting with `EXIT_USAGE`
+#[derive(Debug)]
+struct UsageError(String);
+
+impl fmt::Display for UsageError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for UsageError {}
+
+/// sts rejecting the credentials a run would scan with
+#[derive(Debug)]
+struct IdentityError(RusotoError<GetCallerIdentityError>);
+
+impl fmt::Display for IdentityError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        write!(f, "couldn't verify aws credentials with sts: {}", self.0)
+    }
+}
+
+impl Error for IdentityError {}
+
+/// how a failed run reports its error on stderr, selected with `--error-format`
+#[derive(Clone, Copy, PartialEq)]
+enum ErrorFormat {
+    /// `Error: <message>`
+    Text,
+    /// a single line `{"error": {"kind": ..., "message": ..., "exit_status": ...}}`
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(error_format: &str) -> Result<Self, Self::Err> {
+        match error_format {
+            "text" => Ok(ErrorFormat::Text),
+            "json" => Ok(ErrorFormat::Json),
+            other => Err(format!(
+                "unknown error format {}, expected one of text or json",
+                other
+            )),
+        }
+    }
+}
+
+impl ErrorFormat {
+    /// `--error-format json` in the raw arguments, for arguments which failed to
+    /// parse and so never became `Opts`
+    fn from_args() -> Self {
+        let args = env::args().collect::<Vec<_>>();
+        let json = args.iter().any(|arg| arg == "--error-format=json")
+            || args
+                .windows(2)
+                .any(|pair| pair[0] == "--error-format" && pair[1] == "json");
+        if json {
+            ErrorFormat::Json
+        } else {
+            ErrorFormat::Text
+        }
+    }
+
+    fn report(
+        self,
+        kind: &str,
+        message: &str,
+        exit_status: i32,
+    ) {
+        match self {
+            ErrorFormat::Text => eprintln!("Error: {}", message),
+            ErrorFormat::Json => eprintln!(
+                "{}",
+                serde_json::json!({
+                    "error": {
+                        "kind": kind,
+                        "message": message,
+                        "exit_status": exit_status,
+                    }
+                })
+            ),
+        }
+    }
+}
+
+/// category of the error a run failed with: usage, credentials, network (aws
+/// couldn't be reached), aws (a request was refused), io, or other
+fn error_kind(err: &(dyn Error + 'static)) -> &'static str {
+    fn rusoto<E: Error + 'static>(err: &(dyn Error + 'static)) -> Option<&'static str> {
+        Some(match err.downcast_ref::<RusotoError<E>>()? {
+            RusotoError::Credentials(_) => "credentials",
+            RusotoError::HttpDispatch(_) => "network",
+            _ => "aws",
+        })
+    }
+    if err.is::<UsageError>() {
+        "usage"
+    } else if let Some(IdentityError(err)) = err.downcast_ref() {
+        // any refusal here means the credentials can't be used
+        match err {
+            RusotoError::HttpDispatch(_) => "network",
+            _ => "credentials",
+        }
+    } else if err.is::<CredentialsError>() {
+        "credentials"
+    } else if err.is::<IoError>() {
+        "io"
+    } else {
+        rusoto::<DescribeRepositoriesError>(err)
+            .or_else(|| rusoto::<DescribeImagesError>(err))
+            .or_else(|| rusoto::<BatchGetImageError>(err))
+            .unwrap_or("other")
     }
 }
 
 #[derive(StructOpt)]
+// report modes replacing the per repository report, at most one per run
+#[structopt(group = ArgGroup::with_name("mode"))]
+#[structopt(after_help = "EXIT STATUS:
+    0    success
+    1    error, e.g. failed aws calls
+    2    invalid arguments or output format
+    3    monthly cost exceeds --budget
+    4    policy violation, e.g. --fail-on-untagged")]
 struct Opts {
     #[structopt(long, short, default_value = "tsv")]
-    /// output format: tsv or csv
+    /// output format: tsv, tsv-no-arrow (bare tab separated values without
+    /// decoration or totals, for scripts), csv, delta-csv, influx, openmetrics,
+    /// logfmt, kv (totals as shell variables), excel, parquet, protobuf, json,
+    /// gron, toml, sql, asciidoc, html, summary-markdown (the total and top five repositories,
+    /// for pull request comments), summary-json (just the totals and savings, on one
+    /// line, for dashboards), template, badge, tree, or dot (a graphviz graph
+    /// of the namespace tree, for `dot -Tpng`).
+    /// protobuf-schema prints the .proto schema protobuf reports are encoded against
     format: String,
-    #[structopt(long, short, default_value = "2")]
+    #[structopt(long, default_value = "100")]
+    /// monthly cost at which --format badge turns yellow
+    badge_yellow: f64,
+    #[structopt(long, default_value = "1000")]
+    /// monthly cost at which --format badge turns red
+    badge_red: f64,
+    #[structopt(long)]
+    /// line format for --format template, e.g. "{name}: ${cost}", with
+    /// placeholders named as in --fields
+    template: Option<Template>,
+    #[structopt(long, default_value = "ecr_repositories")]
+    /// table created and inserted into by --format sql
+    table: String,
+    #[structopt(long, default_value = ",", parse(try_from_str = parse_delimiter))]
+    /// field separator of csv output, e.g. `;` for spreadsheets in locales using
+    /// a decimal comma
+    delimiter: u8,
+    #[structopt(long)]
+    /// precede --format html's table with an inline svg bar chart of the
+    /// costliest repositories' monthly costs
+    embed_chart: bool,
+    #[structopt(long, default_value = "10")]
+    /// repositories charted by --embed-chart
+    chart_top: usize,
+    #[structopt(long, short)]
     /// capped number of images for forcast pricing (default 2)
-    cap: usize,
+    cap: Option<usize>,
+    #[structopt(long, conflicts_with = "cap")]
+    /// forecast pricing retaining images pushed within this many days of the
+    /// start of the month, instead of a number of images
+    cap_days: Option<u32>,
+    #[structopt(long, parse(try_from_str = parse_as_of))]
+    /// reckon the month, cutoff, and retention as of this utc date or time, to
+    /// reproduce an earlier month's report. images pushed later are ignored, but
+    /// images deleted since can't be recovered, so past reports may undercount
+    as_of: Option<NaiveDateTime>,
+    #[structopt(
+        long,
+        default_value = "1",
+        parse(try_from_str = parse_period_start_day)
+    )]
+    /// day of the month (1 to 28) billing periods start on, for organizations
+    /// reconciling on a fiscal boundary. images pushed before the latest period
+    /// start count toward the full period, and with --as-of it's the period the
+    /// given date falls in
+    period_start_day: u32,
+    #[structopt(long, parse(try_from_str = parse_size))]
+    /// only report repositories whose aggregate image size is at least this,
+    /// in bytes or with a unit, e.g. 500MB or 1GiB. totals cover just those
+    min_size: Option<i64>,
+    #[structopt(long, use_delimiter = true, value_name = "caps", group = "mode")]
+    /// comma separated numbers of images, e.g. 1,2,5,10, to compare the capped
+    /// monthly cost of each repository under, instead of reporting
+    compare_cap: Vec<usize>,
+    #[structopt(long = "protect-tag")]
+    /// tag pattern (`*` wildcards) of images the forecast always retains
+    protect_tags: Vec<String>,
+    #[structopt(long = "ignore-media-type", value_name = "type")]
+    /// leave out images whose manifest, artifact, or config media type is this,
+    /// before anything is totaled. repeat for more types, e.g.
+    /// application/vnd.dev.cosign.artifact.sig.v1+json (cosign signatures),
+    /// application/spdx+json or application/vnd.cyclonedx+json (sboms), or
+    /// application/vnd.cncf.helm.config.v1+json (helm charts). inspects every
+    /// image's manifest
+    ignore_media_types: Vec<String>,
+    #[structopt(long)]
+    /// print estimated totals across a range of compression ratios
+    sensitivity: bool,
+    #[structopt(long)]
+    /// append additional columns (repository arn, latest image size delta, cost per
+    /// image, largest image and its size) to the default fields
+    extra_columns: bool,
+    #[structopt(long, use_delimiter = true)]
+    /// comma separated columns to report, in order, for tsv, csv, logfmt, excel, json, and asciidoc.
+    /// one of account, name, raw_name, arn, last_pushed, age_days, size,
+    /// size_delta, aggregate_size, artifact_size, capped_size, largest_image,
+    /// largest_image_size, min_size, median_size, max_size, size_stddev,
+    /// reconciled_size, size_discrepancy, images, cost, capped_cost,
+    /// cost_per_image, cost_formula, authors (read from the latest image's
+    /// manifest), layers (averaged across image manifests), percent_of_total,
+    /// latest_only, sampled, latest_cost, or history_cost
+    fields: Vec<Field>,
+    #[structopt(long, use_delimiter = true)]
+    /// comma separated aws profiles to scan and aggregate as accounts
+    profiles: Vec<String>,
+    #[structopt(long, value_name = "alias")]
+    /// report accounts by their alias, looked up with iam list-account-aliases,
+    /// instead of their id or profile name, falling back on those when there's
+    /// no alias or the lookup is denied. a value sets the scanned account's alias
+    /// instead of looking it up
+    account_alias: Option<Option<String>>,
+    #[structopt(long, default_value = "4")]
+    /// maximum number of profiles scanned concurrently
+    concurrency: usize,
+    #[structopt(long, default_value = "10")]
+    /// maximum ecr requests per second, shared by all concurrent scans, 0 for unlimited
+    rate: f64,
+    #[structopt(long)]
+    /// when ecr throttles a request, halve --rate for the rest of the run and
+    /// retry, ramping back up as requests succeed. rate changes are logged
+    throttle_on_error: bool,
+    #[structopt(long, requires = "throttle-on-error")]
+    /// most throttled requests --throttle-on-error retries across the whole run,
+    /// failing with a tally of the retries by operation once they're spent
+    total_retries: Option<usize>,
+    #[structopt(long, short, parse(from_os_str))]
+    /// file to write reports to, required for excel, parquet, and protobuf
+    output: Option<PathBuf>,
+    #[structopt(long)]
+    /// estimated ratio of billed to reported image size (default 0.65)
+    compression: Option<f64>,
+    #[structopt(long)]
+    /// price uncompressed sizes, the worst case, same as --compression 1.0
+    no_compression: bool,
+    #[structopt(long, default_value = "snapshot")]
+    /// how images are billed for the month. snapshot bills the images hosted
+    /// at the start of the month for all of it, ignoring this month's pushes.
+    /// prorated also bills this month's pushes for the rest of the month after
+    /// they were pushed, assuming nothing is deleted
+    cost_model: Model,
+    #[structopt(long, default_value = "1024", parse(try_from_str = parse_gb_base))]
+    /// bytes per billed GB are this cubed, 1024 or 1000. aws bills storage in
+    /// binary gigabytes (GiB), so the default matches the bill
+    gb_base: i64,
+    #[structopt(long)]
+    /// print time spent in each phase and the number of api calls to stderr
+    timings: bool,
+    #[structopt(long, short)]
+    /// don't print the block of warnings about data quality at the end of a run
+    quiet: bool,
+    #[structopt(long, default_value = "text")]
+    /// how a failed run reports its error on stderr: text, or json, a single
+    /// `{"error": {"kind", "message", "exit_status"}}` object whose kind is one of
+    /// usage, credentials, network, aws, io, or other
+    error_format: ErrorFormat,
+    #[structopt(long)]
+    /// page tsv output through $PAGER (less by default) when stdout is a terminal
+    pager: bool,
+    #[structopt(long)]
+    /// copy the report, in the chosen --format, to the system clipboard instead
+    /// of writing it to stdout. on linux a background process keeps the report
+    /// on the x11 or wayland clipboard, exiting once something else is copied
+    clipboard: bool,
+    #[structopt(long, value_name = "command", conflicts_with = "clipboard")]
+    /// stream the report to this shell command's stdin, its output taking the
+    /// report's place, failing when it does. it runs with your privileges and
+    /// environment (aws credentials included), so only pass commands you trust
+    pipe_through: Option<String>,
+    #[structopt(long)]
+    /// leave the totals row out of tsv output
+    no_totals: bool,
+    #[structopt(long, requires = "profiles")]
+    /// precede the tsv totals row with a subtotal row per account and region,
+    /// sorted by account
+    subtotals: bool,
+    #[structopt(long)]
+    /// truncate repository names in tsv output to this many characters, ending
+    /// them with an ellipsis. other formats keep the full name
+    max_name_width: Option<usize>,
+    #[structopt(long)]
+    /// show last pushed dates in tsv output relative to now (or --as-of), e.g.
+    /// "3 days ago". other formats keep absolute timestamps
+    relative_dates: bool,
+    #[structopt(long, group = "mode")]
+    /// print only account level totals, without per repository rows
+    summary_only: bool,
+    #[structopt(long)]
+    /// append a cost_formula column showing how each repository's cost is estimated
+    explain: bool,
+    #[structopt(long, value_name = "pattern")]
+    /// strip the shortest prefix matching this pattern (`*` wildcards), e.g.
+    /// `*/`, from repository names so they line up across accounts, adding a
+    /// raw_name column with the original
+    normalize_names: Option<String>,
+    #[structopt(long)]
+    /// append reconciled_size, counting manifest lists (multi-arch images) as
+    /// the sum of their platform images, and its size_discrepancy from the
+    /// reported aggregate size. inspects every image's manifest
+    reconcile: bool,
+    #[structopt(long)]
+    /// append artifact_size, the blob size of the oci artifacts (helm charts,
+    /// signatures, sboms, ...) hosted alongside images, and count artifacts at
+    /// their blob size in the aggregate size, monthly cost, and --min-size where
+    /// ecr reports them smaller. inspects every image's manifest. artifacts are only seen when hosted in
+    /// the repository, as the referrers api isn't available to the scan
+    artifacts: bool,
+    #[structopt(long, group = "mode")]
+    /// report manifest lists (multi-arch images) referencing platform images
+    /// which have been deleted, by repository, list, and missing digest, instead
+    /// of per repository costs. inspects every image's manifest
+    check_references: bool,
+    #[structopt(long)]
+    /// append min_size, median_size, max_size, and size_stddev columns
+    /// describing the spread of each repository's image sizes
+    stats: bool,
+    #[structopt(long)]
+    /// append a percent_of_total column showing each repository's share of the
+    /// total monthly cost
+    percent: bool,
+    #[structopt(long)]
+    /// append latest_cost and history_cost columns splitting the cost of storing
+    /// the latest image from that of the older images retained with it
+    cost_breakdown: bool,
+    #[structopt(long, value_name = "dollars")]
+    /// roll repositories costing less than this a month up into a single
+    /// `(other)` row after the rest, summing their sizes, counts, and costs.
+    /// totals still include them
+    group_cost_threshold: Option<f64>,
+    #[structopt(long)]
+    /// append a latest_only column and warn about repositories whose only tag
+    /// across all images is `latest`, as overwriting it loses their history
+    warn_on_latest_only: bool,
+    #[structopt(long)]
+    /// count only the first this many images ecr lists per repository, for quick
+    /// approximate reports of huge repositories. ecr lists images in no
+    /// particular order, so sizes and costs of sampled repositories are lower
+    /// bounds. appends a sampled column marking them
+    sample: Option<usize>,
+    #[structopt(long, default_value = "dollars")]
+    /// dollars, or millicents for integer thousandths of a cent that don't round
+    /// small costs away. columns in millicents are suffixed _millicents
+    units: Units,
+    #[structopt(long)]
+    /// round size columns up to a whole number of kib, mib, gib, or tib
+    round_to: Option<Unit>,
+    #[structopt(long)]
+    /// warn on stderr about repositories within this many images of --image-limit
+    near_limit: Option<usize>,
+    #[structopt(long)]
+    /// print a headline to stderr of the storage and monthly cost deleting the
+    /// untagged images and those beyond the cap would reclaim
+    estimate_deletion_savings: bool,
+    #[structopt(long)]
+    /// exit non-zero when any repository has more than this many untagged images
+    fail_on_untagged: Option<usize>,
+    #[structopt(long)]
+    /// exit non-zero when the estimated monthly cost exceeds this many dollars
+    budget: Option<f64>,
+    #[structopt(long, default_value = "20000")]
+    /// images per repository quota, ecr's default unless raised for the account
+    image_limit: usize,
+    #[structopt(long, group = "mode")]
+    /// report cost per tagged image, apportioning shared layers, instead of per repository
+    tag_costs: bool,
+    #[structopt(long, group = "mode")]
+    /// report repositories hosting manifest lists (multi-arch images), their
+    /// combined size and platform count, instead of per repository costs
+    multi_arch: bool,
+    #[structopt(long, conflicts_with = "profiles")]
+    /// list repositories (name, creation time, uri, and tag mutability) without
+    /// fetching any images, in tsv, csv, or json
+    repositories_only: bool,
+    #[structopt(long, conflicts_with = "profiles")]
+    /// print just the repository count, image count, and distinct digest count,
+    /// listing images without any size or cost math, as a quick probe
+    count_only: bool,
+    #[structopt(long, requires = "output-dir", group = "mode")]
+    /// write each repository's images, in tsv, csv, or json, to a file of its
+    /// own in --output-dir, listing the files written instead of reporting
+    output_per_repo: bool,
+    #[structopt(long, parse(from_os_str))]
+    /// directory --output-per-repo writes to, created if needed
+    output_dir: Option<PathBuf>,
+    #[structopt(long, group = "mode")]
+    /// report images, size, and cost by tag prefix across all repositories,
+    /// instead of per repository
+    aggregate_by_tag_prefix: bool,
+    #[structopt(long, group = "mode")]
+    /// report images, size, and cost by platform architecture (amd64, arm64, ...)
+    /// across all repositories, instead of per repository. only the platform
+    /// images of manifest lists record one, others are of unknown architecture
+    by_architecture: bool,
+    #[structopt(long, default_value = "-")]
+    /// tags are grouped by the text before the last occurrence of this separator
+    tag_prefix_separator: String,
+    #[structopt(long, parse(try_from_str = parse_registry_id))]
+    /// 12 digit account id of the registry to scan, defaults to the caller's own
+    registry_id: Option<String>,
+    #[structopt(long)]
+    /// proxy url to reach aws through. defaults to $HTTPS_PROXY (or $https_proxy);
+    /// hosts listed in $NO_PROXY (or $no_proxy) are reached directly
+    proxy: Option<String>,
+    #[structopt(long, parse(from_os_str))]
+    /// file of repository names (`*` wildcards) to scan, one per line, `#` comments
+    include_file: Option<PathBuf>,
+    #[structopt(long, parse(from_os_str))]
+    /// file of repository names (`*` wildcards) to skip, taking precedence over --include-file
+    exclude_file: Option<PathBuf>,
+    #[structopt(long, requires = "image-tag")]
+    /// repository to look up --image-tag in
+    repo: Option<String>,
+    #[structopt(long, requires = "repo")]
+    /// print the details of the image with this tag in --repo instead of a report
+    image_tag: Option<String>,
+    #[structopt(long, parse(from_os_str), conflicts_with = "profiles")]
+    /// report the size and cost of just the images listed in this file, one
+    /// `repository@sha256:...` per line, grouped by repository, in tsv or csv
+    image_digests_file: Option<PathBuf>,
+    #[structopt(long, requires = "image-digests-file")]
+    /// warn about and skip malformed --image-digests-file entries instead of failing
+    skip_malformed: bool,
+    #[structopt(long, default_value = "any")]
+    /// only scan tagged or untagged images, filtered by ecr: tagged, untagged, or any
+    tag_status: TagStatus,
+    #[structopt(long, conflicts_with = "only-immutable")]
+    /// only report repositories whose tags are mutable
+    only_mutable: bool,
+    #[structopt(long)]
+    /// only report repositories whose tags are immutable. repositories of unknown
+    /// mutability are left out of both filters, with a warning
+    only_immutable: bool,
+    #[structopt(long)]
+    /// print the json schema of --format json reports and exit, without scanning
+    emit_schema: bool,
+    #[structopt(long, conflicts_with = "profiles")]
+    /// print the account and arn the credentials belong to, looked up with sts
+    /// get-caller-identity, and exit without scanning
+    whoami: bool,
+    #[structopt(long, conflicts_with = "profiles")]
+    /// print the account and arn being scanned as to stderr before scanning
+    show_identity: bool,
 }
 
-fn load_all_images(
-    ecr: &EcrClient,
-    repository_name: String,
-    next: Option<String>,
-) -> Result<Vec<ImageDetail>, Box<dyn Error>> {
-    let result = ecr
-        .describe_images(DescribeImagesRequest {
-            repository_name: repository_name.clone(),
-            max_results: Some(1_000),
-            next_token: next,
-            ..DescribeImagesRequest::default()
-        })
-        .sync()?;
-    if result.next_token.is_some() {
-        let mut images = result.image_details.unwrap_or_default();
-        images.append(&mut load_all_images(
-            ecr,
-            repository_name,
-            result.next_token,
-        )?);
-        Ok(images)
-    } else {
-        Ok(result.image_details.unwrap_or_default())
+impl Opts {
+    fn pricing(&self) -> Pricing {
+        Pricing {
+            gb_base: self.gb_base,
+            compression: if self.no_compression {
+                1.0
+            } else {
+                self.compression.unwrap_or(Repo::COMPRESSION)
+            },
+        }
+    }
+
+    /// whether a field is asked for with `--fields` or by the template
+    fn uses(
+        &self,
+        field: Field,
+    ) -> bool {
+        self.fields.contains(&field)
+            || self
+                .template
+                .as_ref()
+                .is_some_and(|template| template.uses(field))
     }
 }
 
-fn load_all_repositories(
-    ecr: &EcrClient,
-    next: Option<String>,
-) -> Result<Vec<Repository>, Box<dyn Error>> {
-    let result = ecr
-        .describe_repositories(DescribeRepositoriesRequest {
-            max_results: Some(1_000),
-            next_token: next,
-            ..DescribeRepositoriesRequest::default()
-        })
-        .sync()?;
-    if result.next_token.is_some() {
-        let mut repositories = result.repositories.unwrap_or_default();
-        repositories.append(&mut load_all_repositories(ecr, result.next_token)?);
-        Ok(repositories)
+fn parse_registry_id(registry_id: &str) -> Result<String, String> {
+    if registry_id.len() == 12 && registry_id.chars().all(|c| c.is_ascii_digit()) {
+        Ok(registry_id.to_string())
     } else {
-        Ok(result.repositories.unwrap_or_default())
+        Err(format!("{} is not a 12 digit aws account id", registry_id))
     }
 }
 
-fn pushed_at(details: &ImageDetail) -> NaiveDateTime {
-    NaiveDateTime::from_timestamp(details.image_pushed_at.unwrap_or_default() as i64, 0)
+/// serializable view of a repository shared by the structured output formats
+#[derive(Serialize)]
+struct Record<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    account: Option<&'a str>,
+    name: &'a str,
+    arn: &'a str,
+    last_pushed_at: Option<&'a str>,
+    latest_image_size: i64,
+    aggregate_image_size: i64,
+    recent_image_size: i64,
+    hosted_images: usize,
+    monthly_cost: f64,
+    monthly_capped_cost: f64,
 }
 
-fn repos(
-    ecr: &EcrClient,
-    cap: usize,
-) -> Result<Vec<Repo>, Box<dyn Error>> {
-    let now = Utc::now().naive_utc();
-    let first_of_the_month = NaiveDateTime::new(
-        NaiveDate::from_ymd(now.year(), now.month(), 1),
-        NaiveTime::from_hms(0, 0, 0),
-    );
-    load_all_repositories(&ecr, None)?
-        .into_iter()
-        .try_fold(Vec::new(), |mut repos, repo| {
-            let repository_name = repo.repository_name.unwrap_or_default();
-            let mut images = load_all_images(&ecr, repository_name.clone(), None)?;
-
-            images.retain(|details| pushed_at(details) < first_of_the_month);
-            images.sort_by(|a, b| pushed_at(b).cmp(&pushed_at(a)));
-            let capped_images = images.clone().into_iter().take(cap).collect::<Vec<_>>();
-            repos.push(Repo {
-                name: repository_name,
-                last_pushed_at: images
-                    .iter()
-                    .next()
-                    .map(|details| pushed_at(details).to_string()),
-                latest_image_size: images
-                    .iter()
-                    .next()
-                    .map(|details| details.image_size_in_bytes.unwrap_or_default())
-                    .unwrap_or_default(),
-                aggregate_image_size: images
-                    .iter()
-                    .map(|details| details.image_size_in_bytes.unwrap_or_default())
-                    .sum(),
-                recent_image_size: capped_images
-                    .iter()
-                    .map(|details| details.image_size_in_bytes.unwrap_or_default())
-                    .sum(),
-                hosted_images: images.len(),
-            });
-            Ok(repos)
-        })
+impl<'a> From<&'a Repo> for Record<'a> {
+    fn from(repo: &'a Repo) -> Self {
+        Record {
+            account: repo.account.as_deref(),
+            name: &repo.name,
+            arn: &repo.arn,
+            last_pushed_at: repo.last_pushed_at.as_deref(),
+            latest_image_size: repo.latest_image_size,
+            aggregate_image_size: repo.aggregate_image_size,
+            recent_image_size: repo.recent_image_size,
+            hosted_images: repo.hosted_images,
+            monthly_cost: repo.monthly_cost(),
+            monthly_capped_cost: repo.monthly_capped_cost(),
+        }
+    }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let Opts { format, cap } = Opts::from_args();
-    let ecr = EcrClient::new(Region::default());
-    let mut writer = TabWriter::new(stdout());
-    let mut repos = repos(&ecr, cap)?;
-    repos.sort_by(|a, b| b.latest_image_size.cmp(&a.latest_image_size));
-    let totals: Result<(f64, f64), IoError> =
-        repos
-            .into_iter()
-            .try_fold((0f64, 0f64), |(cost, capped_cost), repo| {
-                let monthly_cost = repo.monthly_cost();
-                let monthly_capped_cost = repo.monthly_capped_cost();
-                let Repo {
-                    name,
-                    last_pushed_at,
-                    latest_image_size,
-                    hosted_images,
-                    ..
-                } = repo;
-                match &format[..] {
-                    "tsv" => {
-                        writeln!(
-                            writer,
-                            "{}\t{}\t{}\t{}\t${:.2}\t=> ${:.2}",
-                            name,
-                            last_pushed_at.unwrap_or_default(),
-                            latest_image_size,
-                            hosted_images,
-                            monthly_cost,
-                            monthly_capped_cost
-                        )?;
-                    }
-                    "csv" => {
-                        println!(
-                            "{},{}, {},{},${:.2},${:.2}",
-                            name,
-                            last_pushed_at.unwrap_or_default(),
-                            latest_image_size,
-                            hosted_images,
-                            monthly_cost,
-                            monthly_capped_cost
-                        );
+/// the committed json schema `Report` documents are described by, to be kept
+/// in step with `Record`, `Metadata`, and `Totals`
+const REPORT_SCHEMA: &str = include_str!("../schema/report.schema.json");
+
+/// the document written by the json, gron, and toml formats, repositories being
+/// either full records or just the selected `--fields`
+#[derive(Serialize)]
+struct Report {
+    metadata: Metadata,
+    repositories: Vec<Value>,
+    totals: Totals,
+}
+
+/// context making archived json reports self describing
+#[derive(Serialize)]
+struct Metadata {
+    region: String,
+    /// the scanned registry's account, absent when scanning `--profiles`
+    account: Option<String>,
+    version: &'static str,
+    generated_at: String,
+}
+
+impl Metadata {
+    /// the account is `--registry-id` when given, otherwise the caller's, as
+    /// sts get-caller-identity reported it before scanning. `--account-alias`
+    /// replaces it with the given or looked up alias
+    fn new(
+        region: &Region,
+        opts: &Opts,
+        caller_account: Option<&str>,
+    ) -> Self {
+        let account = if !opts.profiles.is_empty() {
+            None
+        } else if opts.registry_id.is_some() {
+            opts.registry_id.clone()
+        } else {
+            caller_account.map(String::from)
+        };
+        let account = match &opts.account_alias {
+            Some(Some(alias)) => Some(alias.clone()),
+            Some(None) if account.is_some() => DefaultCredentialsProvider::new()
+                .ok()
+                .and_then(|credentials| account_alias(opts.proxy.as_deref(), credentials, region))
+                .or(account),
+            _ => account,
+        };
+        Metadata {
+            region: region.name().to_string(),
+            account,
+            version: env!("CARGO_PKG_VERSION"),
+            generated_at: Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// a registry to scan and what to compute for each of its repositories
+#[derive(Clone)]
+struct Scan {
+    /// defaults to the caller's own registry
+    registry_id: Option<String>,
+    /// proxy to reach aws through, overriding `HTTPS_PROXY`
+    proxy: Option<String>,
+    tag_status: TagStatus,
+    repositories: Selection,
+    /// `MUTABLE` or `IMMUTABLE`, every repository when absent
+    tag_mutability: Option<&'static str>,
+    retention: Retention,
+    pricing: Pricing,
+    /// repositories hosting less than this many bytes are left out
+    min_size: i64,
+    /// point in time dates are reckoned from, now when absent
+    as_of: Option<NaiveDateTime>,
+    /// day of the month billing periods start on
+    period_start_day: u32,
+    /// images to count per repository, all when absent
+    sample: Option<usize>,
+    /// images of these media types are left out
+    ignore_media_types: Vec<String>,
+    cost_model: Model,
+    /// label profiles' repositories with their account alias, when they have one
+    account_aliases: bool,
+    details: Details,
+}
+
+impl Scan {
+    /// whether a listed repository is selected by name and tag mutability
+    fn selected(
+        &self,
+        repo: &Repository,
+    ) -> bool {
+        let name = repo.repository_name.as_deref().unwrap_or_default();
+        if !self.repositories.selected(name) {
+            return false;
+        }
+        let wanted = match self.tag_mutability {
+            Some(wanted) => wanted,
+            None => return true,
+        };
+        match repo.image_tag_mutability.as_deref() {
+            Some(mutability @ ("MUTABLE" | "IMMUTABLE")) => mutability == wanted,
+            mutability => {
+                WARNINGS.add(
+                    "repositories of unknown tag mutability, left out",
+                    format!("{}: {}", name, mutability.unwrap_or("-")),
+                );
+                false
+            }
+        }
+    }
+}
+
+/// what `repos` computes beyond the per repository summary
+#[derive(Clone, Default)]
+struct Details {
+    tag_costs: bool,
+    authors: bool,
+    multi_arch: bool,
+    layers: bool,
+    architectures: bool,
+    artifacts: bool,
+    references: bool,
+    /// separator of `--aggregate-by-tag-prefix`
+    tag_prefix_separator: Option<String>,
+    images: bool,
+    reconcile: bool,
+    /// min, median, max, and stddev of image sizes
+    stats: bool,
+    /// caps of `--compare-cap`
+    compare_caps: Vec<usize>,
+}
+
+impl Details {
+    /// whether every image's details are kept, rather than the repository
+    /// summed as its pages of images arrive
+    fn needs_images(&self) -> bool {
+        self.tag_costs
+            || self.multi_arch
+            || self.layers
+            || self.reconcile
+            || self.architectures
+            || self.artifacts
+            || self.references
+            || self.tag_prefix_separator.is_some()
+            || self.images
+            || self.stats
+            || !self.compare_caps.is_empty()
+    }
+
+    /// digests of the images whose manifests need to be inspected
+    fn manifest_digests(
+        &self,
+        images: &[ImageDetail],
+    ) -> Vec<String> {
+        let take = if self.tag_costs
+            || self.multi_arch
+            || self.layers
+            || self.reconcile
+            || self.architectures
+            || self.artifacts
+            || self.references
+        {
+            images.len()
+        } else if self.authors {
+            1
+        } else {
+            0
+        };
+        images
+            .iter()
+            .take(take)
+            .filter_map(|details| details.image_digest.clone())
+            .collect()
+    }
+}
+
+/// oci annotation attributing an image to its authors
+const AUTHORS_ANNOTATION: &str = "org.opencontainers.image.authors";
+
+/// time spent in each phase of a run, summed across threads, for `--timings`
+struct Timings {
+    repository_listing: AtomicU64,
+    image_fetching: AtomicU64,
+    image_processing: AtomicU64,
+    formatting: AtomicU64,
+    api_calls: AtomicUsize,
+}
+
+static TIMINGS: Timings = Timings {
+    repository_listing: AtomicU64::new(0),
+    image_fetching: AtomicU64::new(0),
+    image_processing: AtomicU64::new(0),
+    formatting: AtomicU64::new(0),
+    api_calls: AtomicUsize::new(0),
+};
+
+impl Timings {
+    fn record(
+        phase: &AtomicU64,
+        started: Instant,
+    ) {
+        phase.fetch_add(started.elapsed().as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    fn api_call(&self) {
+        self.api_calls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn report(
+        &self,
+        started: Instant,
+    ) {
+        let phase = |phase: &AtomicU64| Duration::from_nanos(phase.load(Ordering::Relaxed));
+        eprintln!("repository listing: {:?}", phase(&self.repository_listing));
+        eprintln!("image fetching: {:?}", phase(&self.image_fetching));
+        eprintln!("image processing: {:?}", phase(&self.image_processing));
+        eprintln!("formatting: {:?}", phase(&self.formatting));
+        eprintln!("total: {:?}", started.elapsed());
+        eprintln!("api calls: {}", self.api_calls.load(Ordering::Relaxed));
+    }
+}
+
+/// data quality caveats gathered during a run and reported together at the
+/// end, so they don't interleave with the report itself
+struct Warnings {
+    /// (kind, detail) in the order they were raised
+    entries: Mutex<Vec<(&'static str, String)>>,
+}
+
+static WARNINGS: Warnings = Warnings {
+    entries: Mutex::new(Vec::new()),
+};
+
+impl Warnings {
+    fn add(
+        &self,
+        kind: &'static str,
+        detail: String,
+    ) {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push((kind, detail));
+    }
+
+    /// prints a `Warnings:` block with a count per kind, if anything was raised
+    fn report(&self) {
+        let entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        if entries.is_empty() {
+            return;
+        }
+        let mut kinds = Vec::<&str>::new();
+        for (kind, _) in entries.iter() {
+            if !kinds.contains(kind) {
+                kinds.push(kind);
+            }
+        }
+        eprintln!("Warnings:");
+        for kind in kinds {
+            let details = entries
+                .iter()
+                .filter(|(other, _)| *other == kind)
+                .map(|(_, detail)| detail)
+                .collect::<Vec<_>>();
+            eprintln!("  {} ({})", kind, details.len());
+            for detail in details {
+                eprintln!("    {}", detail);
+            }
+        }
+    }
+}
+
+/// token bucket every thread's ecr calls pass through, refilled at `--rate`
+/// requests per second and holding at most a second's worth
+struct Throttle {
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    /// requests per second, unlimited when not positive
+    rate: f64,
+    /// `--rate`, which an adaptive rate ramps back up to
+    ceiling: f64,
+    /// with `--throttle-on-error`, slowing down and retrying when throttled
+    adaptive: bool,
+    tokens: f64,
+    refilled: Option<Instant>,
+    /// `--total-retries` left to spend across the run, unbounded when absent
+    retry_budget: Option<usize>,
+    /// retries spent by operation, for the message when the budget runs out
+    retries: Vec<(&'static str, usize)>,
+    exhausted: bool,
+}
+
+static THROTTLE: Throttle = Throttle {
+    bucket: Mutex::new(Bucket {
+        rate: 0.0,
+        ceiling: 0.0,
+        adaptive: false,
+        tokens: 0.0,
+        refilled: None,
+        retry_budget: None,
+        retries: Vec::new(),
+        exhausted: false,
+    }),
+};
+
+/// attempts of a throttled request before its error is given up on
+const THROTTLED_ATTEMPTS: usize = 5;
+
+/// slowest an adaptive rate falls to, in requests per second
+const MIN_ADAPTIVE_RATE: f64 = 0.5;
+
+/// the ecr operation an error type belongs to, e.g. `DescribeImages` for
+/// `DescribeImagesError`
+fn operation<E>() -> &'static str {
+    let name = std::any::type_name::<E>();
+    let name = name.rsplit("::").next().unwrap_or(name);
+    name.strip_suffix("Error").unwrap_or(name)
+}
+
+/// ecr reports throttling with a `ThrottlingException`, which rusoto leaves
+/// unparsed
+fn throttled<E>(err: &RusotoError<E>) -> bool {
+    match err {
+        RusotoError::Unknown(response) => {
+            response.status.as_u16() == 429
+                || response.body_as_str().contains("ThrottlingException")
+        }
+        _ => false,
+    }
+}
+
+impl Throttle {
+    fn set_rate(
+        &self,
+        rate: f64,
+    ) {
+        let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        bucket.rate = rate;
+        bucket.ceiling = rate;
+        bucket.tokens = rate.max(1.0);
+    }
+
+    fn set_adaptive(&self) {
+        self.bucket
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .adaptive = true;
+    }
+
+    fn set_retry_budget(
+        &self,
+        retries: usize,
+    ) {
+        self.bucket
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retry_budget = Some(retries);
+    }
+
+    /// spends a retry of an operation from the budget, returning whether any
+    /// was left. the first request refused one reports where they went
+    fn retry(
+        &self,
+        operation: &'static str,
+    ) -> bool {
+        let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        match bucket.retry_budget {
+            Some(0) => {
+                if !bucket.exhausted {
+                    bucket.exhausted = true;
+                    let spent = bucket
+                        .retries
+                        .iter()
+                        .map(|(operation, retries)| format!("{} {}", retries, operation))
+                        .collect::<Vec<_>>();
+                    eprintln!(
+                        "Retry budget exhausted, giving up on throttled requests after \
+                         retrying {}",
+                        if spent.is_empty() {
+                            "none".into()
+                        } else {
+                            spent.join(", ")
+                        }
+                    );
+                }
+                return false;
+            }
+            Some(left) => bucket.retry_budget = Some(left - 1),
+            None => (),
+        }
+        match bucket
+            .retries
+            .iter_mut()
+            .find(|(name, _)| *name == operation)
+        {
+            Some((_, retries)) => *retries += 1,
+            None => bucket.retries.push((operation, 1)),
+        }
+        true
+    }
+
+    /// halves an adaptive rate after being throttled, returning whether the
+    /// request should be retried
+    fn slow_down(&self) -> bool {
+        let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        if !bucket.adaptive || bucket.rate <= 0.0 {
+            return false;
+        }
+        let rate = (bucket.rate / 2.0).max(MIN_ADAPTIVE_RATE);
+        if rate < bucket.rate {
+            eprintln!(
+                "Throttled by ecr, slowing to {:.1} requests per second",
+                rate
+            );
+        }
+        bucket.rate = rate;
+        bucket.tokens = bucket.tokens.min(rate.max(1.0));
+        true
+    }
+
+    /// ramps an adaptive rate back up by a hundredth of `--rate` per request
+    /// succeeding, so recovering from a halving takes fifty requests
+    fn speed_up(&self) {
+        let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        if !bucket.adaptive || bucket.rate >= bucket.ceiling {
+            return;
+        }
+        bucket.rate = (bucket.rate + bucket.ceiling / 100.0).min(bucket.ceiling);
+        if bucket.rate >= bucket.ceiling {
+            eprintln!(
+                "Recovered from throttling, back to {:.1} requests per second",
+                bucket.ceiling
+            );
+        }
+    }
+
+    /// makes an ecr request once the rate allows, retrying it at a slower rate
+    /// when throttled with `--throttle-on-error`
+    fn call<T: Send + 'static, E: Send + 'static>(
+        &self,
+        mut request: impl FnMut() -> RusotoFuture<T, E>,
+    ) -> Result<T, Box<RusotoError<E>>> {
+        let mut attempts = 1;
+        loop {
+            self.acquire();
+            TIMINGS.api_call();
+            match request().sync().map_err(Box::new) {
+                Err(err)
+                    if attempts < THROTTLED_ATTEMPTS
+                        && throttled(&err)
+                        && self.slow_down()
+                        && self.retry(operation::<E>()) =>
+                {
+                    attempts += 1;
+                }
+                result => {
+                    if result.is_ok() {
+                        self.speed_up();
                     }
-                    _ => (),
+                    return result;
                 }
+            }
+        }
+    }
 
-                Ok((cost + monthly_cost, capped_cost + monthly_capped_cost))
-            });
-    match &format[..] {
-        "tsv" => {
-            let (monthly, capped) = totals?;
-            writeln!(writer, "\t\t\t\t${:.2}\t=> ${:.2}", monthly, capped)?;
-            writer.flush()?;
+    /// blocks until a request may be made
+    fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+                if bucket.rate <= 0.0 {
+                    return;
+                }
+                let now = Instant::now();
+                if let Some(refilled) = bucket.refilled {
+                    let elapsed = now.duration_since(refilled).as_secs_f64();
+                    bucket.tokens =
+                        (bucket.tokens + elapsed * bucket.rate).min(bucket.rate.max(1.0));
+                }
+                bucket.refilled = Some(now);
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                (1.0 - bucket.tokens) / bucket.rate
+            };
+            thread::sleep(Duration::from_secs_f64(wait));
         }
-        _ => (),
     }
+}
 
-    Ok(())
+/// set on the first ctrl-c, after which scanning stops and the repositories
+/// scanned so far are reported
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// field separator of every csv output, `--delimiter`
+static CSV_DELIMITER: AtomicU8 = AtomicU8::new(b',');
+
+fn csv_writer<W: Write>(writer: W) -> csv::Writer<W> {
+    csv::WriterBuilder::new()
+        .delimiter(CSV_DELIMITER.load(Ordering::Relaxed))
+        .from_writer(writer)
+}
+
+/// a single ascii character which can separate csv fields
+fn parse_delimiter(delimiter: &str) -> Result<u8, String> {
+    match delimiter.as_bytes() {
+        [byte] if !matches!(byte, b'"' | b'\r' | b'\n') && byte.is_ascii() => Ok(*byte),
+        _ => Err(format!(
+            "--delimiter must be a single character other than a quote or newline, not {:?}",
+            delimiter
+        )),
+    }
+}
+
+/// account wide sums accumulated while writing repository rows
+#[derive(Default, Serialize)]
+struct Totals {
+    repositories: usize,
+    hosted_images: usize,
+    monthly_cost: f64,
+    monthly_capped_cost: f64,
+    aggregate_image_size: i64,
+    recent_image_size: i64,
+}
+
+impl Totals {
+    fn add(
+        self,
+        repo: &Repo,
+    ) -> Self {
+        Totals {
+            repositories: self.repositories + 1,
+            hosted_images: self.hosted_images + repo.hosted_images,
+            monthly_cost: self.monthly_cost + repo.monthly_cost(),
+            monthly_capped_cost: self.monthly_capped_cost + repo.monthly_capped_cost(),
+            aggregate_image_size: self.aggregate_image_size + repo.aggregate_image_size,
+            recent_image_size: self.recent_image_size + repo.recent_image_size,
+        }
+    }
+}
+
+/// repositories chosen with `--include-file` and `--exclude-file`
+#[derive(Clone)]
+struct Selection {
+    /// every repository when empty
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl Selection {
+    fn selected(
+        &self,
+        name: &str,
+    ) -> bool {
+        let matches =
+            |patterns: &[String]| patterns.iter().any(|pattern| glob_match(pattern, name));
+        (self.include.is_empty() || matches(&self.include)) && !matches(&self.exclude)
+    }
+}
+
+/// names listed in a file, one per line, ignoring blank lines and `#` comments
+fn read_names(path: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| format!("failed to read {}: {}", path.display(), err))?;
+    Ok(contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// which images describe_images returns, filtered by ecr rather than after
+/// they're fetched
+#[derive(Clone, Copy)]
+enum TagStatus {
+    Tagged,
+    Untagged,
+    Any,
+}
+
+impl TagStatus {
+    fn filter(self) -> Option<DescribeImagesFilter> {
+        let tag_status = match self {
+            TagStatus::Tagged => "TAGGED",
+            TagStatus::Untagged => "UNTAGGED",
+            TagStatus::Any => return None,
+        };
+        Some(DescribeImagesFilter {
+            tag_status: Some(tag_status.into()),
+        })
+    }
+}
+
+impl FromStr for TagStatus {
+    type Err = String;
+
+    fn from_str(tag_status: &str) -> Result<Self, Self::Err> {
+        match tag_status {
+            "tagged" => Ok(TagStatus::Tagged),
+            "untagged" => Ok(TagStatus::Untagged),
+            "any" => Ok(TagStatus::Any),
+            other => Err(format!(
+                "unknown tag status {}, expected one of tagged, untagged, or any",
+                other
+            )),
+        }
+    }
+}
+
+/// how the capped forecast decides which images a repository retains. ecr
+/// lists each digest once, along with all of its tags, so caps already count
+/// distinct digests rather than tags
+#[derive(Clone)]
+struct Retention {
+    cap: Cap,
+    protect_tags: Vec<String>,
+}
+
+/// the images retained beyond those protected by tag
+#[derive(Clone, Copy)]
+enum Cap {
+    /// the most recent number of images
+    Images(usize),
+    /// those pushed within a number of days before the cutoff
+    Days(u32),
+}
+
+impl Retention {
+    /// images tagged with a protected tag are never pruned, even beyond the cap
+    fn protected(
+        &self,
+        details: &ImageDetail,
+    ) -> bool {
+        details.image_tags.iter().flatten().any(|tag| {
+            self.protect_tags
+                .iter()
+                .any(|pattern| glob_match(pattern, tag))
+        })
+    }
+
+    /// retained size under each of several image caps, in a single pass over
+    /// images sorted most recent first
+    fn compare_caps(
+        &self,
+        images: &[ImageDetail],
+        caps: &[usize],
+    ) -> Vec<i64> {
+        let mut sizes = vec![0; caps.len()];
+        for (index, details) in images.iter().enumerate() {
+            let size = details.image_size_in_bytes.unwrap_or_default();
+            let protected = self.protected(details);
+            for (cap, retained) in caps.iter().zip(&mut sizes) {
+                if index < *cap || protected {
+                    *retained += size;
+                }
+            }
+        }
+        sizes
+    }
+}
+
+/// matches text against a pattern where `*` matches any run of characters
+fn glob_match(
+    pattern: &str,
+    text: &str,
+) -> bool {
+    match pattern.find('*') {
+        None => pattern == text,
+        Some(star) => {
+            let (prefix, rest) = (&pattern[..star], &pattern[star + 1..]);
+            text.starts_with(prefix)
+                && text
+                    .char_indices()
+                    .map(|(start, _)| start)
+                    .chain(Some(text.len()))
+                    .filter(|start| *start >= prefix.len())
+                    .any(|start| glob_match(rest, &text[start..]))
+        }
+    }
+}
+
+/// the rest of a name after the shortest prefix matching a pattern, the
+/// whole name when no prefix matches or nothing would remain
+fn strip_prefix<'a>(
+    pattern: &str,
+    name: &'a str,
+) -> &'a str {
+    name.char_indices()
+        .map(|(start, _)| start)
+        .skip(1)
+        .find(|start| glob_match(pattern, &name[..*start]))
+        .map_or(name, |start| &name[start..])
+}
+
+/// visits every image of a repository as its page arrives, following
+/// `next_token` across pages, and returns how many were visited
+///
+/// a digest listed on more than one page would otherwise be counted twice, so
+/// repeats are warned about and dropped. the pages, images, and bytes listed
+/// are logged with `RUST_LOG=debug`
+fn each_image(
+    ecr: &impl Ecr,
+    registry_id: Option<String>,
+    repository_name: String,
+    tag_status: TagStatus,
+    limit: Option<usize>,
+    mut visit: impl FnMut(ImageDetail),
+) -> Result<usize, Box<dyn Error>> {
+    let mut listed = 0;
+    let mut bytes = 0;
+    let mut digests = HashSet::new();
+    let mut pages = 0;
+    let mut next_token = None;
+    loop {
+        let remaining = limit.map(|limit| limit.saturating_sub(listed));
+        let request = DescribeImagesRequest {
+            registry_id: registry_id.clone(),
+            repository_name: repository_name.clone(),
+            filter: tag_status.filter(),
+            max_results: Some(remaining.map_or(1_000, |remaining| remaining.min(1_000) as i64)),
+            next_token,
+            ..DescribeImagesRequest::default()
+        };
+        let result = THROTTLE
+            .call(|| ecr.describe_images(request.clone()))
+            .map_err(|err| *err)?;
+        pages += 1;
+        let page = result.image_details.unwrap_or_default();
+        if page.is_empty() {
+            empty_page("describe_images", &repository_name, &result.next_token);
+        }
+        for mut details in page {
+            if let Some(digest) = &details.image_digest {
+                if !digests.insert(digest.clone()) {
+                    WARNINGS.add(
+                        "images listed more than once while paginating, counted once",
+                        format!("{}@{}", repository_name, digest),
+                    );
+                    continue;
+                }
+            }
+            // every image repeats its registry and repository, which are known
+            // already, so drop them as pages arrive rather than hold a copy per image
+            details.registry_id = None;
+            details.repository_name = None;
+            listed += 1;
+            bytes += details.image_size_in_bytes.unwrap_or_default();
+            visit(details);
+        }
+        next_token = result.next_token;
+        // an empty page may still carry a token, so follow it regardless
+        if next_token.is_none() || limit.is_some_and(|limit| listed >= limit) {
+            break;
+        }
+    }
+    debug!(
+        "describe_images listed {} images of {} bytes across {} pages for {}",
+        listed, bytes, pages, repository_name
+    );
+    Ok(listed)
+}
+
+/// ecr may return pages without any items, which are expected for empty
+/// repositories but otherwise worth knowing about when debugging with
+/// `RUST_LOG=debug`
+fn empty_page(
+    operation: &str,
+    target: &str,
+    next_token: &Option<String>,
+) {
+    if next_token.is_some() {
+        debug!(
+            "{} returned an empty page for {} with a next token, following it",
+            operation, target
+        );
+    } else {
+        debug!("{} returned an empty final page for {}", operation, target);
+    }
+}
+
+/// the details of one tagged image, fetched by image id
+fn tagged_image(
+    ecr: &impl Ecr,
+    registry_id: Option<String>,
+    repository_name: &str,
+    tag: &str,
+) -> Result<ImageDetail, Box<dyn Error>> {
+    let not_found = || format!("no image tagged {} in repository {}", tag, repository_name);
+    let request = DescribeImagesRequest {
+        registry_id,
+        repository_name: repository_name.to_string(),
+        image_ids: Some(vec![ImageIdentifier {
+            image_tag: Some(tag.to_string()),
+            ..ImageIdentifier::default()
+        }]),
+        ..DescribeImagesRequest::default()
+    };
+    let result = THROTTLE
+        .call(|| ecr.describe_images(request.clone()))
+        .map_err(|err| match *err {
+            RusotoError::Service(DescribeImagesError::ImageNotFound(_)) => not_found(),
+            err => err.to_string(),
+        })?;
+    Ok(result
+        .image_details
+        .unwrap_or_default()
+        .into_iter()
+        .next()
+        .ok_or_else(not_found)?)
+}
+
+/// `repository@sha256:<hex>` entries of an `--image-digests-file`, in order
+fn read_image_digests(
+    path: &Path,
+    skip_malformed: bool,
+) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let mut entries = Vec::new();
+    for entry in read_names(path)? {
+        let parsed = entry.split_once('@').filter(|(repository, digest)| {
+            !repository.is_empty()
+                && digest.strip_prefix("sha256:").is_some_and(|hex| {
+                    hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit())
+                })
+        });
+        match parsed {
+            Some((repository, digest)) => entries.push((repository.into(), digest.into())),
+            None if skip_malformed => WARNINGS.add("skipped malformed image digests", entry),
+            None => {
+                return Err(UsageError(format!(
+                    "malformed entry {} in {}, expected repository@sha256:<digest>",
+                    entry,
+                    path.display()
+                ))
+                .into())
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// the details of the given images of a repository, fetched by digest. as
+/// ecr fails a whole batch over one missing image, a failed batch is retried
+/// image by image so the missing ones can be reported and skipped
+fn digest_images(
+    ecr: &impl Ecr,
+    registry_id: Option<String>,
+    repository_name: &str,
+    digests: &[String],
+) -> Result<Vec<ImageDetail>, Box<dyn Error>> {
+    let describe = |digests: &[String]| {
+        let request = DescribeImagesRequest {
+            registry_id: registry_id.clone(),
+            repository_name: repository_name.to_string(),
+            image_ids: Some(
+                digests
+                    .iter()
+                    .map(|digest| ImageIdentifier {
+                        image_digest: Some(digest.clone()),
+                        ..ImageIdentifier::default()
+                    })
+                    .collect(),
+            ),
+            ..DescribeImagesRequest::default()
+        };
+        THROTTLE
+            .call(|| ecr.describe_images(request.clone()))
+            .map(|result| result.image_details.unwrap_or_default())
+    };
+    let mut images = Vec::new();
+    // describe_images accepts at most 100 image ids per call
+    for batch in digests.chunks(100) {
+        let err = match describe(batch) {
+            Ok(details) => {
+                images.extend(details);
+                continue;
+            }
+            Err(err) => *err,
+        };
+        match err {
+            RusotoError::Service(DescribeImagesError::RepositoryNotFound(_)) => {
+                WARNINGS.add("repositories not found", repository_name.to_string());
+                break;
+            }
+            RusotoError::Service(DescribeImagesError::ImageNotFound(_)) => {
+                for digest in batch {
+                    match describe(std::slice::from_ref(digest)).map_err(|err| *err) {
+                        Ok(details) => images.extend(details),
+                        Err(RusotoError::Service(DescribeImagesError::ImageNotFound(_))) => {
+                            WARNINGS.add(
+                                "images not found",
+                                format!("{}@{}", repository_name, digest),
+                            )
+                        }
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+            }
+            err => return Err(err.into()),
+        }
+    }
+    Ok(images)
+}
+
+/// images of an `--image-digests-file` with a subtotal per repository, the
+/// repositories in the order they're first listed
+fn write_digest_images(
+    out: &mut dyn Write,
+    repositories: &[(String, Vec<ImageDetail>)],
+    format: &str,
+    pricing: Pricing,
+) -> Result<(), Box<dyn Error>> {
+    let size = |details: &ImageDetail| details.image_size_in_bytes.unwrap_or_default();
+    let row = |repository: &str, details: &ImageDetail| {
+        [
+            repository.to_string(),
+            details.image_digest.clone().unwrap_or_default(),
+            details.image_tags.as_deref().unwrap_or_default().join(","),
+            size(details).to_string(),
+            format::cost(pricing.cost(size(details))),
+        ]
+    };
+    match format {
+        "tsv" => {
+            let mut writer = TabWriter::new(out);
+            for (repository, images) in repositories {
+                for details in images {
+                    writeln!(writer, "{}", row(repository, details).join("\t"))?;
+                }
+                let total = images.iter().map(size).sum();
+                writeln!(
+                    writer,
+                    "{}\t\t\t{}\t{}",
+                    repository,
+                    total,
+                    format::cost(pricing.cost(total))
+                )?;
+            }
+            writer.flush()?;
+        }
+        "csv" => {
+            let mut csv = csv_writer(out);
+            csv.write_record(["repository", "digest", "tags", "size", "monthly_cost"])?;
+            for (repository, images) in repositories {
+                for details in images {
+                    csv.write_record(row(repository, details))?;
+                }
+            }
+            csv.flush()?;
+        }
+        other => {
+            return Err(UsageError(format!(
+                "--image-digests-file supports tsv or csv, not {}",
+                other
+            ))
+            .into())
+        }
+    }
+    Ok(())
+}
+
+fn write_image(
+    out: &mut dyn Write,
+    details: &ImageDetail,
+    pricing: Pricing,
+) -> Result<(), IoError> {
+    let size = details.image_size_in_bytes.unwrap_or_default();
+    let mut writer = TabWriter::new(&mut *out);
+    writeln!(
+        writer,
+        "repository\t{}",
+        details.repository_name.as_deref().unwrap_or_default()
+    )?;
+    writeln!(
+        writer,
+        "tags\t{}",
+        details.image_tags.as_deref().unwrap_or_default().join(", ")
+    )?;
+    writeln!(
+        writer,
+        "digest\t{}",
+        details.image_digest.as_deref().unwrap_or_default()
+    )?;
+    writeln!(writer, "size\t{} ({})", size, format::bytes(size))?;
+    writeln!(writer, "pushed\t{}", pushed_at(details))?;
+    writeln!(writer, "monthly cost\t{}", format::cost(pricing.cost(size)))?;
+    writer.flush()
+}
+
+/// registry scale tallied by `--count-only`
+#[derive(Default)]
+struct Counts {
+    repositories: usize,
+    images: usize,
+    distinct_digests: usize,
+}
+
+/// lists the selected repositories' images just to count them, repositories
+/// deleted since they were listed being skipped as in `repos`
+fn count(
+    ecr: &impl Ecr,
+    scan: &Scan,
+) -> Result<Counts, Box<dyn Error>> {
+    let mut counts = Counts::default();
+    let mut digests = HashSet::new();
+    let repositories = load_all_repositories(ecr, scan.registry_id.clone(), None)?;
+    for repo in repositories.iter().filter(|repo| scan.selected(repo)) {
+        if interrupted() {
+            break;
+        }
+        let repository_name = repo.repository_name.clone().unwrap_or_default();
+        let images = match each_image(
+            ecr,
+            scan.registry_id.clone(),
+            repository_name.clone(),
+            scan.tag_status,
+            None,
+            |details| digests.extend(details.image_digest),
+        ) {
+            Err(err)
+                if matches!(
+                    err.downcast_ref(),
+                    Some(RusotoError::Service(
+                        DescribeImagesError::RepositoryNotFound(_)
+                    ))
+                ) =>
+            {
+                WARNINGS.add("repositories deleted during the scan", repository_name);
+                continue;
+            }
+            result => result?,
+        };
+        counts.repositories += 1;
+        counts.images += images;
+    }
+    counts.distinct_digests = digests.len();
+    Ok(counts)
+}
+
+fn write_counts(
+    out: &mut dyn Write,
+    counts: &Counts,
+) -> Result<(), IoError> {
+    let mut writer = TabWriter::new(out);
+    writeln!(writer, "repositories\t{}", counts.repositories)?;
+    writeln!(writer, "images\t{}", counts.images)?;
+    writeln!(writer, "distinct_digests\t{}", counts.distinct_digests)?;
+    writer.flush()
+}
+
+/// repository level attributes alone, for `--repositories-only`
+fn write_repositories(
+    out: &mut dyn Write,
+    repositories: &[Repository],
+    format: &str,
+) -> Result<(), Box<dyn Error>> {
+    const HEADERS: [&str; 4] = ["name", "created_at", "uri", "tag_mutability"];
+    let rows = repositories.iter().map(|repo| {
+        [
+            repo.repository_name.clone().unwrap_or_default(),
+            repo.created_at
+                .filter(|seconds| seconds.is_finite())
+                .and_then(|seconds| DateTime::from_timestamp(seconds.trunc() as i64, 0))
+                .map(|created_at| created_at.naive_utc().to_string())
+                .unwrap_or_default(),
+            repo.repository_uri.clone().unwrap_or_default(),
+            repo.image_tag_mutability.clone().unwrap_or_default(),
+        ]
+    });
+    match format {
+        "tsv" => {
+            let mut writer = TabWriter::new(out);
+            for row in rows {
+                writeln!(writer, "{}", row.join("\t"))?;
+            }
+            writer.flush()?;
+        }
+        "csv" => {
+            let mut csv = csv_writer(out);
+            csv.write_record(HEADERS)?;
+            for row in rows {
+                csv.write_record(&row)?;
+            }
+            csv.flush()?;
+        }
+        "json" => {
+            let repositories = rows
+                .map(|row| {
+                    HEADERS
+                        .iter()
+                        .map(|header| header.to_string())
+                        .zip(row.iter().cloned().map(Value::String))
+                        .collect::<serde_json::Map<_, _>>()
+                })
+                .collect::<Vec<_>>();
+            writeln!(out, "{}", serde_json::to_string_pretty(&repositories)?)?;
+        }
+        other => {
+            return Err(UsageError(format!(
+                "--repositories-only supports tsv, csv, or json, not {}",
+                other
+            ))
+            .into())
+        }
+    }
+    Ok(())
+}
+
+fn load_all_repositories(
+    ecr: &impl Ecr,
+    registry_id: Option<String>,
+    next: Option<String>,
+) -> Result<Vec<Repository>, Box<dyn Error>> {
+    let request = DescribeRepositoriesRequest {
+        registry_id: registry_id.clone(),
+        max_results: Some(1_000),
+        next_token: next,
+        ..DescribeRepositoriesRequest::default()
+    };
+    let result = THROTTLE
+        .call(|| ecr.describe_repositories(request.clone()))
+        .map_err(|err| *err)?;
+    let mut repositories = result.repositories.unwrap_or_default();
+    if repositories.is_empty() {
+        empty_page(
+            "describe_repositories",
+            registry_id.as_deref().unwrap_or("the default registry"),
+            &result.next_token,
+        );
+    }
+    if result.next_token.is_some() {
+        repositories.append(&mut load_all_repositories(
+            ecr,
+            registry_id,
+            result.next_token,
+        )?);
+    }
+    Ok(repositories)
+}
+
+/// lists a registry's repositories a page at a time, sending each page as it
+/// arrives and stopping early once nothing receives them, returning how many
+/// were listed
+fn stream_repositories(
+    ecr: &impl Ecr,
+    registry_id: Option<String>,
+    pages: SyncSender<Vec<Repository>>,
+) -> Result<usize, Box<RusotoError<DescribeRepositoriesError>>> {
+    let started = Instant::now();
+    let mut listed = 0;
+    let mut next_token = None;
+    loop {
+        let request = DescribeRepositoriesRequest {
+            registry_id: registry_id.clone(),
+            max_results: Some(1_000),
+            next_token,
+            ..DescribeRepositoriesRequest::default()
+        };
+        let result = THROTTLE.call(|| ecr.describe_repositories(request.clone()))?;
+        let repositories = result.repositories.unwrap_or_default();
+        if repositories.is_empty() {
+            empty_page(
+                "describe_repositories",
+                registry_id.as_deref().unwrap_or("the default registry"),
+                &result.next_token,
+            );
+        }
+        listed += repositories.len();
+        next_token = result.next_token;
+        if pages.send(repositories).is_err() || next_token.is_none() {
+            Timings::record(&TIMINGS.repository_listing, started);
+            return Ok(listed);
+        }
+    }
+}
+
+/// apportions layer storage across the root images (those that aren't the
+/// children of a manifest list) of a repository
+fn tag_costs(
+    images: &[ImageDetail],
+    manifests: &HashMap<String, Manifest>,
+) -> Vec<TagCost> {
+    let children = manifests
+        .values()
+        .flat_map(|manifest| manifest.manifests.iter().map(|child| child.digest.as_str()))
+        .collect::<HashSet<_>>();
+    let blobs = |digest: &str| {
+        let mut blobs = HashMap::new();
+        let mut pending = vec![digest];
+        while let Some(digest) = pending.pop() {
+            if let Some(manifest) = manifests.get(digest) {
+                for blob in manifest.layers.iter().chain(&manifest.config) {
+                    blobs.insert(blob.digest.as_str(), blob.size);
+                }
+                pending.extend(manifest.manifests.iter().map(|child| child.digest.as_str()));
+            }
+        }
+        blobs
+    };
+    let roots = images
+        .iter()
+        .filter_map(|details| {
+            let digest = details.image_digest.as_deref()?;
+            if children.contains(digest) {
+                return None;
+            }
+            Some((details, blobs(digest)))
+        })
+        .collect::<Vec<_>>();
+    let mut references = HashMap::<&str, usize>::new();
+    for (_, blobs) in &roots {
+        for digest in blobs.keys() {
+            *references.entry(digest).or_default() += 1;
+        }
+    }
+    roots
+        .iter()
+        .map(|(details, blobs)| TagCost {
+            tags: match details.image_tags.as_ref() {
+                Some(tags) if !tags.is_empty() => tags.join(","),
+                _ => details.image_digest.clone().unwrap_or_default(),
+            },
+            size: if blobs.is_empty() {
+                // manifest unavailable, fall back on the reported size
+                details.image_size_in_bytes.unwrap_or_default() as f64
+            } else {
+                blobs
+                    .iter()
+                    .map(|(digest, size)| *size as f64 / references[digest] as f64)
+                    .sum()
+            },
+        })
+        .collect()
+}
+
+/// total size with each manifest list counted as the sum of the platform
+/// images it references rather than its reported size, and those platform
+/// images counted only through their list. platform images that aren't hosted
+/// fall back on the sum of their manifest's blobs
+fn reconciled_size(
+    images: &[ImageDetail],
+    manifests: &HashMap<String, Manifest>,
+) -> i64 {
+    let sizes = images
+        .iter()
+        .filter_map(|details| {
+            Some((
+                details.image_digest.as_deref()?,
+                details.image_size_in_bytes.unwrap_or_default(),
+            ))
+        })
+        .collect::<HashMap<_, _>>();
+    let children = manifests
+        .values()
+        .flat_map(|manifest| manifest.manifests.iter().map(|child| child.digest.as_str()))
+        .collect::<HashSet<_>>();
+    let child_size = |digest: &str| {
+        sizes.get(digest).copied().unwrap_or_else(|| {
+            manifests
+                .get(digest)
+                .map(|manifest| {
+                    manifest
+                        .layers
+                        .iter()
+                        .chain(&manifest.config)
+                        .map(|blob| blob.size)
+                        .sum()
+                })
+                .unwrap_or_default()
+        })
+    };
+    images
+        .iter()
+        .filter(|details| {
+            !details
+                .image_digest
+                .as_deref()
+                .is_some_and(|digest| children.contains(digest))
+        })
+        .map(|details| {
+            match details
+                .image_digest
+                .as_deref()
+                .and_then(|digest| manifests.get(digest))
+                .filter(|manifest| !manifest.manifests.is_empty())
+            {
+                Some(list) => list
+                    .manifests
+                    .iter()
+                    .map(|child| child_size(&child.digest))
+                    .sum(),
+                None => details.image_size_in_bytes.unwrap_or_default(),
+            }
+        })
+        .sum()
+}
+
+/// images and size by architecture. per platform images take the platform
+/// their manifest list gives them, the lists themselves are counted through
+/// those images, and anything else is of unknown architecture, single platform
+/// manifests only recording theirs in a config blob which isn't fetched
+fn architectures(
+    images: &[ImageDetail],
+    manifests: &HashMap<String, Manifest>,
+) -> HashMap<String, TagPrefix> {
+    let platforms = manifests
+        .values()
+        .flat_map(|manifest| &manifest.manifests)
+        .filter_map(|child| Some((child.digest.as_str(), child.platform.as_ref()?.name())))
+        .collect::<HashMap<_, _>>();
+    let mut architectures = HashMap::<String, TagPrefix>::new();
+    for details in images {
+        let digest = details.image_digest.as_deref().unwrap_or_default();
+        if manifests
+            .get(digest)
+            .is_some_and(|manifest| !manifest.manifests.is_empty())
+        {
+            continue;
+        }
+        let architecture = architectures
+            .entry(
+                platforms
+                    .get(digest)
+                    .cloned()
+                    .unwrap_or_else(|| TagPrefix::UNKNOWN_ARCHITECTURE.into()),
+            )
+            .or_default();
+        architecture.images += 1;
+        architecture.size += details.image_size_in_bytes.unwrap_or_default();
+    }
+    architectures
+}
+
+/// child manifests of the fetched manifest lists missing from the repository,
+/// sorted by list then child digest
+fn broken_references(
+    manifests: &HashMap<String, Manifest>,
+    hosted_digests: &HashSet<String>,
+) -> Vec<BrokenReference> {
+    let mut broken = manifests
+        .iter()
+        .flat_map(|(list, manifest)| {
+            manifest
+                .manifests
+                .iter()
+                .filter(|child| !hosted_digests.contains(&child.digest))
+                .map(move |child| BrokenReference {
+                    list: list.clone(),
+                    child: child.digest.clone(),
+                })
+        })
+        .collect::<Vec<_>>();
+    broken.sort_by(|a, b| (&a.list, &a.child).cmp(&(&b.list, &b.child)));
+    broken
+}
+
+/// oci artifacts among the images
+#[derive(Default)]
+struct Artifacts {
+    /// combined blob size
+    size: i64,
+    /// bytes by which their blobs exceed their reported sizes
+    unreported: i64,
+}
+
+fn artifacts(
+    images: &[ImageDetail],
+    manifests: &HashMap<String, Manifest>,
+) -> Artifacts {
+    images
+        .iter()
+        .filter_map(|details| {
+            let manifest = manifests.get(details.image_digest.as_deref()?)?;
+            if !manifest.is_artifact() {
+                return None;
+            }
+            Some((
+                manifest.blob_size(),
+                details.image_size_in_bytes.unwrap_or_default(),
+            ))
+        })
+        .fold(Artifacts::default(), |artifacts, (size, reported)| {
+            Artifacts {
+                size: artifacts.size + size,
+                unreported: artifacts.unreported + (size - reported).max(0),
+            }
+        })
+}
+
+/// manifest lists among the images and the per platform images they reference
+fn multi_arch(
+    images: &[ImageDetail],
+    manifests: &HashMap<String, Manifest>,
+) -> MultiArch {
+    let sizes = images
+        .iter()
+        .filter_map(|details| {
+            Some((
+                details.image_digest.as_deref()?,
+                details.image_size_in_bytes.unwrap_or_default(),
+            ))
+        })
+        .collect::<HashMap<_, _>>();
+    manifests
+        .values()
+        .filter(|manifest| !manifest.manifests.is_empty())
+        .fold(MultiArch::default(), |multi_arch, manifest| MultiArch {
+            images: multi_arch.images + 1,
+            size: multi_arch.size
+                + manifest
+                    .manifests
+                    .iter()
+                    .filter_map(|child| sizes.get(child.digest.as_str()))
+                    .sum::<i64>(),
+            platforms: multi_arch.platforms.max(manifest.manifests.len()),
+        })
+}
+
+/// ecr reports push times as (fractional) epoch seconds. Values chrono can't
+/// represent, which would otherwise panic, fall back on the epoch
+fn pushed_at(details: &ImageDetail) -> NaiveDateTime {
+    details
+        .image_pushed_at
+        .filter(|seconds| seconds.is_finite())
+        .and_then(|seconds| DateTime::from_timestamp(seconds.trunc() as i64, 0))
+        .unwrap_or_default()
+        .naive_utc()
+}
+
+/// where an image sorts among a repository's, later pushes being greater and
+/// batch pushes sharing a timestamp falling back on the digest, lower digests
+/// being greater, to keep the latest image and the capped set stable across runs
+type Order = (NaiveDateTime, Reverse<Option<String>>);
+
+fn order(details: &ImageDetail) -> Order {
+    (pushed_at(details), Reverse(details.image_digest.clone()))
+}
+
+/// one of the most recent hosted images `Summary` keeps
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Newest {
+    order: Order,
+    size: i64,
+    untagged: bool,
+    protected: bool,
+}
+
+/// a repository's images summed one at a time, as their pages arrive, so only
+/// the most recent few need to be kept: those an image cap retains, and at
+/// least the latest and previous images
+struct Summary<'a> {
+    scan: &'a Scan,
+    period_start: NaiveDateTime,
+    /// pushes retained by `--cap-days` are no earlier than this
+    earliest_retained: Option<NaiveDateTime>,
+    /// the most recent hosted images, the least recent on top
+    newest: BinaryHeap<Reverse<Newest>>,
+    total_images: usize,
+    untagged_images: usize,
+    unsized_images: usize,
+    tagged: bool,
+    only_latest: bool,
+    billed_image_size: f64,
+    hosted_images: usize,
+    aggregate_image_size: i64,
+    /// hosted images retained without counting the most recent, and the
+    /// untagged among them
+    retained_image_size: i64,
+    retained_untagged_size: i64,
+    /// size and order of the largest hosted image, the least recent of equally
+    /// large ones, and its first tag or else its digest
+    largest: Option<(i64, Reverse<Order>)>,
+    largest_image: Option<String>,
+}
+
+impl<'a> Summary<'a> {
+    fn new(
+        scan: &'a Scan,
+        period_start: NaiveDateTime,
+    ) -> Self {
+        Summary {
+            scan,
+            period_start,
+            // `run` rejects caps reaching back before any representable time
+            earliest_retained: match scan.retention.cap {
+                Cap::Days(days) => {
+                    period_start.checked_sub_signed(chrono::Duration::days(days.into()))
+                }
+                Cap::Images(_) => None,
+            },
+            newest: BinaryHeap::new(),
+            total_images: 0,
+            untagged_images: 0,
+            unsized_images: 0,
+            tagged: false,
+            only_latest: true,
+            billed_image_size: 0.0,
+            hosted_images: 0,
+            aggregate_image_size: 0,
+            retained_image_size: 0,
+            retained_untagged_size: 0,
+            largest: None,
+            largest_image: None,
+        }
+    }
+
+    /// how many of the most recent hosted images are kept
+    fn capacity(&self) -> usize {
+        match self.scan.retention.cap {
+            Cap::Images(cap) => cap.max(2),
+            Cap::Days(_) => 2,
+        }
+    }
+
+    fn add(
+        &mut self,
+        details: &ImageDetail,
+    ) {
+        let tags = details.image_tags.as_deref().unwrap_or_default();
+        let size = details.image_size_in_bytes.unwrap_or_default();
+        self.total_images += 1;
+        if tags.is_empty() {
+            self.untagged_images += 1;
+        }
+        if details.image_size_in_bytes.is_none() {
+            self.unsized_images += 1;
+        }
+        self.tagged |= !tags.is_empty();
+        self.only_latest &= tags.iter().all(|tag| tag == "latest");
+        self.billed_image_size += self
+            .scan
+            .cost_model
+            .strategy()
+            .billed_bytes(details, self.period_start);
+        let order = order(details);
+        if order.0 >= self.period_start {
+            return;
+        }
+        self.hosted_images += 1;
+        self.aggregate_image_size += size;
+        let protected = self.scan.retention.protected(details);
+        let retained = protected
+            || match self.scan.retention.cap {
+                Cap::Days(_) => self
+                    .earliest_retained
+                    .is_none_or(|earliest| order.0 >= earliest),
+                // only known once every image has been seen
+                Cap::Images(_) => false,
+            };
+        if retained {
+            self.retained_image_size += size;
+            if tags.is_empty() {
+                self.retained_untagged_size += size;
+            }
+        }
+        let largest = (size, Reverse(order.clone()));
+        if self.largest.as_ref().is_none_or(|other| largest > *other) {
+            self.largest = Some(largest);
+            self.largest_image = tags.first().or(details.image_digest.as_ref()).cloned();
+        }
+        self.newest.push(Reverse(Newest {
+            order,
+            size,
+            untagged: tags.is_empty(),
+            protected,
+        }));
+        if self.newest.len() > self.capacity() {
+            self.newest.pop();
+        }
+    }
+
+    /// the kept images, most recent first
+    fn newest(&self) -> Vec<&Newest> {
+        let mut newest = self
+            .newest
+            .iter()
+            .map(|Reverse(image)| image)
+            .collect::<Vec<_>>();
+        newest.sort_by(|a, b| b.cmp(a));
+        newest
+    }
+
+    /// bytes the capped forecast retains, and the untagged bytes among them
+    fn retained(&self) -> (i64, i64) {
+        let (mut size, mut untagged) = (self.retained_image_size, self.retained_untagged_size);
+        if let Cap::Images(cap) = self.scan.retention.cap {
+            for image in self
+                .newest()
+                .into_iter()
+                .take(cap)
+                .filter(|image| !image.protected)
+            {
+                size += image.size;
+                if image.untagged {
+                    untagged += image.size;
+                }
+            }
+        }
+        (size, untagged)
+    }
+}
+
+/// aws partition a region lives in, used to qualify arns
+fn partition(region: &Region) -> &'static str {
+    match region.name() {
+        name if name.starts_with("cn-") => "aws-cn",
+        name if name.starts_with("us-gov-") => "aws-us-gov",
+        _ => "aws",
+    }
+}
+
+/// prefer the arn ecr reports, falling back on constructing one
+fn repository_arn(
+    region: &Region,
+    repo: &Repository,
+) -> String {
+    repo.repository_arn.clone().unwrap_or_else(|| {
+        format!(
+            "arn:{}:ecr:{}:{}:repository/{}",
+            partition(region),
+            region.name(),
+            repo.registry_id.as_deref().unwrap_or_default(),
+            repo.repository_name.as_deref().unwrap_or_default()
+        )
+    })
+}
+
+fn repos(
+    ecr: &(impl Ecr + Sync),
+    region: &Region,
+    scan: &Scan,
+) -> Result<Vec<Repo>, Box<dyn Error>> {
+    let now = scan.as_of.unwrap_or_else(|| Utc::now().naive_utc());
+    let period_start = NaiveDateTime::new(
+        period_start(now.date(), scan.period_start_day),
+        NaiveTime::MIN,
+    );
+    // repositories are scanned as their pages arrive, fetching the images of
+    // one page's repositories while the next is still being listed
+    thread::scope(|scope| {
+        let (sender, pages) = mpsc::sync_channel(1);
+        let registry_id = scan.registry_id.clone();
+        let listing = scope.spawn(move || stream_repositories(ecr, registry_id, sender));
+        let repos: Result<Vec<Repo>, Box<dyn Error>> = pages
+            .into_iter()
+            .flatten()
+            .filter(|repo| scan.selected(repo))
+            .take_while(|_| !interrupted())
+            .try_fold(Vec::new(), |mut repos, repo| {
+                let arn = repository_arn(region, &repo);
+                let repository_name = repo.repository_name.unwrap_or_default();
+                let started = Instant::now();
+                // the default report sums images as their pages arrive, only the
+                // detailed modes keeping every image's details
+                let full = !scan.ignore_media_types.is_empty() || scan.details.needs_images();
+                let mut summary = Summary::new(scan, period_start);
+                let mut images = Vec::new();
+                let mut seen = 0;
+                let listed = match each_image(
+                    ecr,
+                    scan.registry_id.clone(),
+                    repository_name.clone(),
+                    scan.tag_status,
+                    // one more than the sample reveals whether it's partial
+                    scan.sample.map(|sample| sample + 1),
+                    |details| {
+                        seen += 1;
+                        if scan.sample.is_some_and(|sample| seen > sample) {
+                            return;
+                        }
+                        // images pushed since didn't exist yet
+                        if scan.as_of.is_some() && pushed_at(&details) > now {
+                            return;
+                        }
+                        if full {
+                            images.push(details);
+                        } else {
+                            summary.add(&details);
+                        }
+                    },
+                ) {
+                    // deleted since it was listed
+                    Err(err)
+                        if matches!(
+                            err.downcast_ref(),
+                            Some(RusotoError::Service(
+                                DescribeImagesError::RepositoryNotFound(_)
+                            ))
+                        ) =>
+                    {
+                        WARNINGS.add("repositories deleted during the scan", repository_name);
+                        return Ok(repos);
+                    }
+                    result => result?,
+                };
+                Timings::record(&TIMINGS.image_fetching, started);
+                let sampled = scan.sample.is_some_and(|sample| listed > sample);
+
+                let started = Instant::now();
+                // every image's manifest is needed to tell its media type, and is
+                // kept for the detailed modes rather than fetched twice
+                let ignored_manifests = if scan.ignore_media_types.is_empty() {
+                    None
+                } else {
+                    let digests = images
+                        .iter()
+                        .filter_map(|details| details.image_digest.clone())
+                        .collect::<Vec<_>>();
+                    let manifests = manifest::manifests(
+                        ecr,
+                        scan.registry_id.clone(),
+                        &repository_name,
+                        &digests,
+                    )?;
+                    images.retain(|details| {
+                        !details
+                            .image_digest
+                            .as_deref()
+                            .and_then(|digest| manifests.get(digest))
+                            .is_some_and(|manifest| {
+                                manifest.has_media_type(&scan.ignore_media_types)
+                            })
+                    });
+                    Some(manifests)
+                };
+                for details in &images {
+                    summary.add(details);
+                }
+                if summary.unsized_images > 0 {
+                    WARNINGS.add(
+                        "images without a size, counted as 0 bytes",
+                        format!("{}: {} images", repository_name, summary.unsized_images),
+                    );
+                }
+                // children pushed this month still satisfy a list's references
+                let hosted_digests = if scan.details.references {
+                    images
+                        .iter()
+                        .filter_map(|details| details.image_digest.clone())
+                        .collect()
+                } else {
+                    HashSet::new()
+                };
+                images.retain(|details| pushed_at(details) < period_start);
+                images.sort_by_key(|details| Reverse(order(details)));
+                let newest = summary.newest();
+                // artifacts only add to the size, so without them a repository
+                // below --min-size can be left out before fetching any manifests
+                if !scan.details.artifacts && summary.aggregate_image_size < scan.min_size {
+                    Timings::record(&TIMINGS.image_processing, started);
+                    return Ok(repos);
+                }
+                let latest_digest = newest.first().and_then(|image| image.order.1 .0.clone());
+                let digests = if full {
+                    scan.details.manifest_digests(&images)
+                } else if scan.details.authors {
+                    latest_digest.iter().cloned().collect()
+                } else {
+                    Vec::new()
+                };
+                let manifests = if let Some(manifests) = ignored_manifests {
+                    let digests = digests.iter().collect::<HashSet<_>>();
+                    manifests
+                        .into_iter()
+                        .filter(|(digest, _)| digests.contains(digest))
+                        .collect()
+                } else if digests.is_empty() {
+                    HashMap::new()
+                } else {
+                    manifest::manifests(ecr, scan.registry_id.clone(), &repository_name, &digests)?
+                };
+                let tag_costs = if scan.details.tag_costs {
+                    tag_costs(&images, &manifests)
+                } else {
+                    Vec::new()
+                };
+                let multi_arch = if scan.details.multi_arch {
+                    multi_arch(&images, &manifests)
+                } else {
+                    MultiArch::default()
+                };
+                let architectures = if scan.details.architectures {
+                    architectures(&images, &manifests)
+                } else {
+                    HashMap::new()
+                };
+                let mut tag_prefixes = HashMap::<String, TagPrefix>::new();
+                if let Some(separator) = &scan.details.tag_prefix_separator {
+                    for details in &images {
+                        let tag_prefix = tag_prefixes
+                            .entry(TagPrefix::of(details, separator).to_string())
+                            .or_default();
+                        tag_prefix.images += 1;
+                        tag_prefix.size += details.image_size_in_bytes.unwrap_or_default();
+                    }
+                }
+                let layers = images
+                    .iter()
+                    .filter_map(|details| manifests.get(details.image_digest.as_deref()?))
+                    .filter(|manifest| !manifest.layers.is_empty())
+                    .map(|manifest| manifest.layers.len())
+                    .collect::<Vec<_>>();
+                let average_layers = if layers.is_empty() {
+                    None
+                } else {
+                    Some(layers.iter().sum::<usize>() as f64 / layers.len() as f64)
+                };
+                let authors = latest_digest
+                    .as_deref()
+                    .and_then(|digest| manifests.get(digest))
+                    .and_then(|manifest| manifest.annotations.get(AUTHORS_ANNOTATION).cloned());
+                let broken_references = if scan.details.references {
+                    broken_references(&manifests, &hosted_digests)
+                } else {
+                    Vec::new()
+                };
+                let artifacts = if scan.details.artifacts {
+                    artifacts(&images, &manifests)
+                } else {
+                    Artifacts::default()
+                };
+                let aggregate_image_size = summary.aggregate_image_size + artifacts.unreported;
+                let billed_image_size =
+                    summary.billed_image_size.round() as i64 + artifacts.unreported;
+                if aggregate_image_size < scan.min_size {
+                    Timings::record(&TIMINGS.image_processing, started);
+                    return Ok(repos);
+                }
+                let (recent_image_size, retained_untagged_size) = summary.retained();
+                repos.push(Repo {
+                    account: None,
+                    name: repository_name,
+                    raw_name: None,
+                    arn,
+                    last_pushed_at: newest.first().map(|image| image.order.0.to_string()),
+                    age_days: newest.first().map(|image| (now - image.order.0).num_days()),
+                    latest_image_size: newest.first().map_or(0, |image| image.size),
+                    previous_image_size: newest.get(1).map(|image| image.size),
+                    largest_image: summary.largest_image.clone(),
+                    largest_image_size: summary.largest.as_ref().map_or(0, |(size, _)| *size),
+                    size_stats: SizeStats::of(
+                        images
+                            .iter()
+                            .map(|details| details.image_size_in_bytes.unwrap_or_default())
+                            .collect(),
+                    ),
+                    aggregate_image_size,
+                    artifact_size: artifacts.size,
+                    reconciled_image_size: if scan.details.reconcile {
+                        reconciled_size(&images, &manifests)
+                    } else {
+                        aggregate_image_size
+                    },
+                    recent_image_size,
+                    // everything beyond the cap, plus the untagged images within it
+                    reclaimable_image_size: summary.aggregate_image_size - recent_image_size
+                        + retained_untagged_size,
+                    billed_image_size,
+                    hosted_images: summary.hosted_images,
+                    total_images: summary.total_images,
+                    pricing: scan.pricing,
+                    untagged_images: summary.untagged_images,
+                    latest_only: summary.tagged && summary.only_latest,
+                    sampled,
+                    tag_costs,
+                    authors,
+                    average_layers,
+                    multi_arch,
+                    tag_prefixes,
+                    architectures,
+                    images: if scan.details.images {
+                        images
+                            .iter()
+                            .map(|details| Image {
+                                tags: details.image_tags.clone().unwrap_or_default(),
+                                digest: details.image_digest.clone().unwrap_or_default(),
+                                size: details.image_size_in_bytes.unwrap_or_default(),
+                                pushed_at: pushed_at(details),
+                            })
+                            .collect()
+                    } else {
+                        Vec::new()
+                    },
+                    compared_caps: scan
+                        .retention
+                        .compare_caps(&images, &scan.details.compare_caps),
+                    broken_references,
+                });
+                Timings::record(&TIMINGS.image_processing, started);
+                Ok(repos)
+            });
+        let listed = listing
+            .join()
+            .unwrap_or_else(|panic| panic::resume_unwind(panic));
+        let repos = repos?;
+        // printed as it happens, even with --quiet, so an empty report isn't
+        // mistaken for a failed one
+        if listed.map_err(|err| *err)? == 0 {
+            eprintln!("No repositories found in region {}", region.name());
+        }
+        Ok(repos)
+    })
+}
+
+fn profile_repos(
+    profile: &str,
+    region: &Region,
+    scan: &Scan,
+) -> Result<Vec<Repo>, Box<dyn Error>> {
+    let mut credentials = ProfileProvider::new()?;
+    credentials.set_profile(profile);
+    let account = if scan.account_aliases {
+        account_alias(scan.proxy.as_deref(), credentials.clone(), region)
+    } else {
+        None
+    }
+    .unwrap_or_else(|| profile.to_string());
+    let ecr = EcrClient::new_with(
+        http::client(scan.proxy.as_deref())?,
+        credentials,
+        region.clone(),
+    );
+    Ok(repos(&ecr, region, scan)?
+        .into_iter()
+        .map(|repo| Repo {
+            account: Some(account.clone()),
+            ..repo
+        })
+        .collect())
+}
+
+/// an account's alias from iam list-account-aliases, none when it has none or
+/// the caller isn't allowed to ask
+fn account_alias<P>(
+    proxy: Option<&str>,
+    credentials: P,
+    region: &Region,
+) -> Option<String>
+where
+    P: ProvideAwsCredentials + Send + Sync + 'static,
+    P::Future: Send,
+{
+    TIMINGS.api_call();
+    IamClient::new_with(http::client(proxy).ok()?, credentials, region.clone())
+        .list_account_aliases(ListAccountAliasesRequest::default())
+        .sync()
+        .ok()?
+        .account_aliases
+        .into_iter()
+        .next()
+}
+
+/// scans profiles, at most `concurrency` at a time, skipping (and reporting)
+/// those that fail rather than abandoning the whole run
+fn all_profile_repos(
+    profiles: &[String],
+    region: &Region,
+    scan: &Scan,
+    concurrency: usize,
+) -> Vec<Repo> {
+    profiles
+        .chunks(concurrency.max(1))
+        .flat_map(|batch| {
+            batch
+                .iter()
+                .cloned()
+                .map(|profile| {
+                    let (region, scan) = (region.clone(), scan.clone());
+                    let name = profile.clone();
+                    let handle = thread::spawn(move || {
+                        profile_repos(&profile, &region, &scan).map_err(|err| err.to_string())
+                    });
+                    (name, handle)
+                })
+                .collect::<Vec<_>>()
+        })
+        .filter_map(|(profile, handle)| {
+            // a panicking scan is skipped like a failing one, so the totals
+            // aren't silently missing an account
+            let err = match handle.join() {
+                Ok(Ok(repos)) => return Some(repos),
+                Ok(Err(err)) => err,
+                Err(panic) => format!(
+                    "scan panicked: {}",
+                    panic
+                        .downcast_ref::<&str>()
+                        .copied()
+                        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+                        .unwrap_or("unknown cause")
+                ),
+            };
+            WARNINGS.add("skipped profiles", format!("{}: {}", profile, err));
+            None
+        })
+        .flatten()
+        .collect()
+}
+
+/// writes a workbook with typed numeric cells and a totals row of sum formulas
+fn write_excel(
+    repos: &[Repo],
+    fields: &[Field],
+    style: &Style,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let bold = Format::new().set_bold();
+    let currency = Format::new().set_num_format("$#,##0.00");
+    let bold_currency = currency.clone().set_bold();
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    for (col, field) in fields.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, field.column(style), &bold)?;
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    for (index, repo) in repos.iter().enumerate() {
+        let row = index as u32 + 1;
+        for (col, field) in fields.iter().enumerate() {
+            let col = col as u16;
+            match field.value(repo, style) {
+                Cell::Text(text) => sheet.write_string(row, col, text.unwrap_or_default())?,
+                Cell::Integer(integer) => sheet.write_number(row, col, integer as f64)?,
+                Cell::Size(bytes, unit) => sheet.write_number(
+                    row,
+                    col,
+                    unit.map(|unit| unit.round_up(bytes)).unwrap_or(bytes) as f64,
+                )?,
+                Cell::Cost(cost) => sheet.write_number_with_format(row, col, cost, &currency)?,
+                Cell::Millicents(millicents) => sheet.write_number(row, col, millicents as f64)?,
+                Cell::Number(Some(number)) | Cell::Percent(Some(number)) => {
+                    sheet.write_number(row, col, number)?
+                }
+                Cell::Count(Some(count)) => sheet.write_number(row, col, count as f64)?,
+                Cell::Number(None) | Cell::Percent(None) | Cell::Count(None) => {
+                    sheet.write_string(row, col, "-")?
+                }
+            };
+        }
+    }
+    let totals_row = repos.len() as u32 + 1;
+    let summed = |field: &Field| matches!(field, Field::Cost | Field::CappedCost);
+    // labelled in the first column without a total, so the label can't
+    // overwrite one
+    if let Some(col) = fields.iter().position(|field| !summed(field)) {
+        sheet.write_string_with_format(totals_row, col as u16, "total", &bold)?;
+    }
+    for (col, field) in fields.iter().enumerate() {
+        if summed(field) {
+            let col = col as u16;
+            sheet.write_formula_with_format(
+                totals_row,
+                col,
+                format!("=SUM({})", cell_range(1, col, totals_row - 1, col)).as_str(),
+                if style.units == Units::Dollars {
+                    &bold_currency
+                } else {
+                    &bold
+                },
+            )?;
+        }
+    }
+    sheet.autofit();
+    workbook.save(path)?;
+    Ok(())
+}
+
+/// one row per root image of each repository, most costly first
+fn write_tag_costs(
+    out: &mut dyn Write,
+    repos: &[Repo],
+    format: &str,
+    pricing: Pricing,
+) -> Result<(), IoError> {
+    let mut rows = Vec::new();
+    for repo in repos {
+        let mut tag_costs = repo.tag_costs.iter().collect::<Vec<_>>();
+        tag_costs.sort_by(|a, b| b.size.partial_cmp(&a.size).unwrap_or(cmp::Ordering::Equal));
+        rows.extend(tag_costs.into_iter().map(|tag_cost| (repo, tag_cost)));
+    }
+    if format == "csv" {
+        let mut headers = vec!["repository", "tags", "size", "monthly_cost"];
+        if repos.iter().any(|repo| repo.account.is_some()) {
+            headers.insert(0, "account");
+        }
+        let mut csv = csv_writer(&mut *out);
+        csv.write_record(&headers)?;
+        for (repo, tag_cost) in rows {
+            let mut record = repo.account.iter().cloned().collect::<Vec<_>>();
+            record.extend(vec![
+                repo.name.clone(),
+                tag_cost.tags.clone(),
+                format!("{:.0}", tag_cost.size),
+                format::cost(tag_cost.monthly_cost(pricing)),
+            ]);
+            csv.write_record(&record)?;
+        }
+        return csv.flush();
+    }
+    let mut writer = TabWriter::new(out);
+    for (repo, tag_cost) in rows {
+        writeln!(
+            writer,
+            "{}{}\t{}\t{:.0}\t{}",
+            repo.account
+                .as_ref()
+                .map(|account| format!("{}\t", account))
+                .unwrap_or_default(),
+            repo.name,
+            tag_cost.tags,
+            tag_cost.size,
+            format::cost(tag_cost.monthly_cost(pricing))
+        )?;
+    }
+    writer.flush()
+}
+
+/// capped monthly cost at each compared cap, side by side per repository with
+/// a row of totals
+fn write_compare_caps(
+    out: &mut dyn Write,
+    repos: &[Repo],
+    caps: &[usize],
+    format: &str,
+    pricing: Pricing,
+) -> Result<(), IoError> {
+    let headers = caps.iter().map(|cap| format!("cap {}", cap));
+    let costs = |sizes: &[i64]| {
+        sizes
+            .iter()
+            .map(|size| format::cost(pricing.cost(*size)))
+            .collect::<Vec<_>>()
+    };
+    let totals = caps
+        .iter()
+        .enumerate()
+        .map(|(index, _)| repos.iter().map(|repo| repo.compared_caps[index]).sum())
+        .collect::<Vec<i64>>();
+    if format == "csv" {
+        let mut csv = csv_writer(out);
+        csv.write_record(Some("repository".to_string()).into_iter().chain(headers))?;
+        for repo in repos {
+            csv.write_record(
+                Some(repo.name.clone())
+                    .into_iter()
+                    .chain(costs(&repo.compared_caps)),
+            )?;
+        }
+        return csv.flush();
+    }
+    let mut writer = TabWriter::new(out);
+    writeln!(
+        writer,
+        "repository\t{}",
+        headers.collect::<Vec<_>>().join("\t")
+    )?;
+    for repo in repos {
+        writeln!(
+            writer,
+            "{}\t{}",
+            repo.name,
+            costs(&repo.compared_caps).join("\t")
+        )?;
+    }
+    writeln!(writer, "\t{}", costs(&totals).join("\t"))?;
+    writer.flush()
+}
+
+/// a file name for a repository's detail, keeping only characters safe on
+/// any filesystem so names like `team/service` can't escape the directory
+fn per_repo_file_name(
+    repo: &Repo,
+    extension: &str,
+) -> String {
+    let name = repo
+        .account
+        .iter()
+        .chain(Some(&repo.name))
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join("_");
+    let mut file_name = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    if file_name.starts_with('.') {
+        file_name.insert(0, '_');
+    }
+    format!("{}.{}", file_name, extension)
+}
+
+/// one file of image detail per repository, then the files written
+fn write_per_repo(
+    out: &mut dyn Write,
+    repos: &[Repo],
+    format: &str,
+    pricing: Pricing,
+    dir: &Path,
+) -> Result<(), Box<dyn Error>> {
+    if !["tsv", "csv", "json"].contains(&format) {
+        return Err(UsageError(format!(
+            "--output-per-repo supports tsv, csv, or json, not {}",
+            format
+        ))
+        .into());
+    }
+    fs::create_dir_all(dir)
+        .map_err(|err| format!("failed to create {}: {}", dir.display(), err))?;
+    let mut summary = TabWriter::new(out);
+    for repo in repos {
+        let path = dir.join(per_repo_file_name(repo, format));
+        let mut file = fs::File::create(&path)
+            .map_err(|err| format!("failed to create {}: {}", path.display(), err))?;
+        match format {
+            "json" => {
+                let images = repo
+                    .images
+                    .iter()
+                    .map(|image| {
+                        serde_json::json!({
+                            "tags": image.tags,
+                            "digest": image.digest,
+                            "size": image.size,
+                            "pushed_at": image.pushed_at.to_string(),
+                            "monthly_cost": pricing.cost(image.size),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                writeln!(file, "{}", serde_json::to_string_pretty(&images)?)?;
+            }
+            "csv" => {
+                let mut csv = csv_writer(&mut file);
+                csv.write_record(["tags", "digest", "size", "pushed_at", "monthly_cost"])?;
+                for image in &repo.images {
+                    csv.write_record(&[
+                        image.tags.join(","),
+                        image.digest.clone(),
+                        image.size.to_string(),
+                        image.pushed_at.to_string(),
+                        format::cost(pricing.cost(image.size)),
+                    ])?;
+                }
+                csv.flush()?;
+            }
+            _ => {
+                let mut writer = TabWriter::new(&mut file);
+                for image in &repo.images {
+                    writeln!(
+                        writer,
+                        "{}\t{}\t{}\t{}\t{}",
+                        image.tags.join(","),
+                        image.digest,
+                        image.size,
+                        image.pushed_at,
+                        format::cost(pricing.cost(image.size))
+                    )?;
+                }
+                writer.flush()?;
+            }
+        }
+        writeln!(summary, "{}\t{} images", path.display(), repo.images.len())?;
+    }
+    writeln!(
+        summary,
+        "{} files written to {}",
+        repos.len(),
+        dir.display()
+    )?;
+    Ok(summary.flush()?)
+}
+
+fn write_multi_arch(
+    out: &mut dyn Write,
+    repos: &[Repo],
+    format: &str,
+) -> Result<(), IoError> {
+    let multi_arch = repos
+        .iter()
+        .filter(|repo| repo.multi_arch.images > 0)
+        .collect::<Vec<_>>();
+    let accounts = repos.iter().any(|repo| repo.account.is_some());
+    let account = |repo: &Repo| repo.account.clone().into_iter();
+    if format == "csv" {
+        let mut csv = csv_writer(&mut *out);
+        let mut headers = vec!["repository", "multi_arch_images", "size", "platforms"];
+        if accounts {
+            headers.insert(0, "account");
+        }
+        csv.write_record(&headers)?;
+        for repo in multi_arch {
+            csv.write_record(account(repo).chain(vec![
+                repo.name.clone(),
+                repo.multi_arch.images.to_string(),
+                repo.multi_arch.size.to_string(),
+                repo.multi_arch.platforms.to_string(),
+            ]))?;
+        }
+        return csv.flush();
+    }
+    let mut writer = TabWriter::new(&mut *out);
+    for repo in &multi_arch {
+        writeln!(
+            writer,
+            "{}\t{} multi-arch images\t{}\t{} platforms",
+            account(repo)
+                .chain(Some(repo.name.clone()))
+                .collect::<Vec<_>>()
+                .join("\t"),
+            repo.multi_arch.images,
+            repo.multi_arch.size,
+            repo.multi_arch.platforms
+        )?;
+    }
+    writeln!(
+        writer,
+        "{} repositories\t{} multi-arch images\t{}",
+        multi_arch.len(),
+        multi_arch
+            .iter()
+            .map(|repo| repo.multi_arch.images)
+            .sum::<usize>(),
+        multi_arch
+            .iter()
+            .map(|repo| repo.multi_arch.size)
+            .sum::<i64>()
+    )?;
+    writer.flush()
+}
+
+/// one row per missing child of a manifest list, a repository's rows together
+fn write_broken_references(
+    out: &mut dyn Write,
+    repos: &[Repo],
+    format: &str,
+) -> Result<(), IoError> {
+    let rows = repos.iter().flat_map(|repo| {
+        repo.broken_references.iter().map(move |reference| {
+            repo.account
+                .iter()
+                .chain([&repo.name, &reference.list, &reference.child])
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+    });
+    for repo in repos
+        .iter()
+        .filter(|repo| !repo.broken_references.is_empty())
+    {
+        WARNINGS.add(
+            "repositories with manifest lists referencing deleted images",
+            repo.name.clone(),
+        );
+    }
+    if format == "csv" {
+        let mut csv = csv_writer(&mut *out);
+        let mut headers = vec!["repository", "manifest_list", "missing_digest"];
+        if repos.iter().any(|repo| repo.account.is_some()) {
+            headers.insert(0, "account");
+        }
+        csv.write_record(&headers)?;
+        for row in rows {
+            csv.write_record(&row)?;
+        }
+        return csv.flush();
+    }
+    let mut writer = TabWriter::new(&mut *out);
+    for row in rows {
+        writeln!(writer, "{}", row.join("\t"))?;
+    }
+    writer.flush()
+}
+
+/// tag prefixes rolled up across repositories, largest first
+fn write_tag_prefixes(
+    out: &mut dyn Write,
+    repos: &[Repo],
+    format: &str,
+    pricing: Pricing,
+) -> Result<(), IoError> {
+    let mut totals = HashMap::<&str, TagPrefix>::new();
+    for (prefix, tag_prefix) in repos.iter().flat_map(|repo| &repo.tag_prefixes) {
+        let total = totals.entry(prefix).or_default();
+        *total = mem::take(total).add(tag_prefix);
+    }
+    let mut totals = totals.into_iter().collect::<Vec<_>>();
+    totals.sort_by_key(|(prefix, tag_prefix)| (Reverse(tag_prefix.size), *prefix));
+    let cost = |tag_prefix: &TagPrefix| pricing.cost(tag_prefix.size);
+    if format == "csv" {
+        let mut csv = csv_writer(&mut *out);
+        csv.write_record(["tag_prefix", "images", "size", "monthly_cost"])?;
+        for (prefix, tag_prefix) in &totals {
+            csv.write_record(&[
+                prefix.to_string(),
+                tag_prefix.images.to_string(),
+                tag_prefix.size.to_string(),
+                format::cost(cost(tag_prefix)),
+            ])?;
+        }
+        return csv.flush();
+    }
+    let mut writer = TabWriter::new(&mut *out);
+    for (prefix, tag_prefix) in &totals {
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}",
+            prefix,
+            tag_prefix.images,
+            tag_prefix.size,
+            format::cost(cost(tag_prefix))
+        )?;
+    }
+    writer.flush()
+}
+
+/// architectures rolled up across repositories, largest first
+fn write_architectures(
+    out: &mut dyn Write,
+    repos: &[Repo],
+    format: &str,
+    pricing: Pricing,
+) -> Result<(), IoError> {
+    let mut totals = HashMap::<&str, TagPrefix>::new();
+    for (name, architecture) in repos.iter().flat_map(|repo| &repo.architectures) {
+        let total = totals.entry(name).or_default();
+        *total = mem::take(total).add(architecture);
+    }
+    if let Some(unknown) = totals.get(TagPrefix::UNKNOWN_ARCHITECTURE) {
+        WARNINGS.add(
+            "images of unknown architecture, not part of a manifest list",
+            format!("{} images, {} bytes", unknown.images, unknown.size),
+        );
+    }
+    let mut totals = totals.into_iter().collect::<Vec<_>>();
+    totals.sort_by_key(|(name, architecture)| (Reverse(architecture.size), *name));
+    let cost = |architecture: &TagPrefix| pricing.cost(architecture.size);
+    if format == "csv" {
+        let mut csv = csv_writer(&mut *out);
+        csv.write_record(["architecture", "images", "size", "monthly_cost"])?;
+        for (name, architecture) in &totals {
+            csv.write_record(&[
+                name.to_string(),
+                architecture.images.to_string(),
+                architecture.size.to_string(),
+                format::cost(cost(architecture)),
+            ])?;
+        }
+        return csv.flush();
+    }
+    let mut writer = TabWriter::new(&mut *out);
+    for (name, architecture) in &totals {
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}",
+            name,
+            architecture.images,
+            architecture.size,
+            format::cost(cost(architecture))
+        )?;
+    }
+    writer.flush()
+}
+
+/// escapes commas, equals signs, and spaces in influx line protocol tag values
+fn influx_tag(value: &str) -> String {
+    value.chars().fold(String::new(), |mut escaped, c| {
+        if let ',' | '=' | ' ' = c {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        escaped
+    })
+}
+
+/// account level aggregates alone, for `--summary-only`
+fn write_summary(
+    mut out: &mut dyn Write,
+    totals: &Totals,
+    format: &str,
+    region: &Region,
+    opts: &Opts,
+    caller_account: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    match format {
+        "tsv" => {
+            let mut writer = TabWriter::new(&mut *out);
+            writeln!(writer, "repositories\t{}", totals.repositories)?;
+            writeln!(writer, "images\t{}", totals.hosted_images)?;
+            writeln!(writer, "size\t{}", totals.aggregate_image_size)?;
+            writeln!(writer, "monthly cost\t{}", format::cost(totals.monthly_cost))?;
+            writeln!(
+                writer,
+                "capped monthly cost\t=> {}",
+                format::cost(totals.monthly_capped_cost)
+            )?;
+            writer.flush()?;
+        }
+        "csv" => {
+            let mut csv = csv_writer(&mut *out);
+            csv.write_record([
+                "repositories",
+                "hosted_images",
+                "aggregate_image_size",
+                "monthly_cost",
+                "monthly_capped_cost",
+            ])?;
+            csv.write_record(&[
+                totals.repositories.to_string(),
+                totals.hosted_images.to_string(),
+                totals.aggregate_image_size.to_string(),
+                format::cost(totals.monthly_cost),
+                format::cost(totals.monthly_capped_cost),
+            ])?;
+            csv.flush()?;
+        }
+        "json" | "gron" => {
+            let value = serde_json::json!({
+                "metadata": Metadata::new(region, opts, caller_account),
+                "totals": totals,
+            });
+            if format == "json" {
+                writeln!(out, "{}", serde_json::to_string_pretty(&value)?)?;
+            } else {
+                gron::write(&mut out, "json", &value)?;
+            }
+        }
+        "influx" => writeln!(
+            out,
+            "ecr_total_cost repositories={}i,hosted_images={}i,aggregate_image_size={}i,monthly_cost={},monthly_capped_cost={} {}",
+            totals.repositories,
+            totals.hosted_images,
+            totals.aggregate_image_size,
+            totals.monthly_cost,
+            totals.monthly_capped_cost,
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        )?,
+        other => {
+            return Err(UsageError(format!(
+                "--summary-only isn't supported for {} output",
+                other
+            ))
+            .into())
+        }
+    }
+    Ok(())
+}
+
+/// a shields.io endpoint badge of the account's monthly cost
+/// https://shields.io/badges/endpoint-badge
+fn write_badge(
+    out: &mut dyn Write,
+    totals: &Totals,
+    opts: &Opts,
+) -> Result<(), Box<dyn Error>> {
+    let color = match totals.monthly_cost {
+        cost if cost >= opts.badge_red => "red",
+        cost if cost >= opts.badge_yellow => "yellow",
+        _ => "green",
+    };
+    writeln!(
+        out,
+        "{}",
+        serde_json::json!({
+            "schemaVersion": 1,
+            "label": "ecr cost",
+            "message": format::cost(totals.monthly_cost),
+            "color": color,
+        })
+    )?;
+    Ok(())
+}
+
+/// single quoted for the shell, embedded quotes closing and reopening the quoting
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// account totals as shell variable assignments, for
+/// `eval "$(ecr-insights --format kv)"`
+fn write_kv(
+    out: &mut dyn Write,
+    totals: &Totals,
+    region: &Region,
+) -> Result<(), IoError> {
+    writeln!(out, "ECR_REGION={}", shell_quote(region.name()))?;
+    writeln!(out, "ECR_REPO_COUNT={}", totals.repositories)?;
+    writeln!(out, "ECR_TOTAL_IMAGES={}", totals.hosted_images)?;
+    writeln!(out, "ECR_TOTAL_SIZE={}", totals.aggregate_image_size)?;
+    writeln!(out, "ECR_TOTAL_COST={:.2}", totals.monthly_cost)?;
+    writeln!(
+        out,
+        "ECR_TOTAL_CAPPED_COST={:.2}",
+        totals.monthly_capped_cost
+    )
+}
+
+/// repositories in a `--format summary-markdown` table
+const SUMMARY_MARKDOWN_TOP: usize = 5;
+
+/// the account's headline numbers as a single line json object, for dashboards
+/// which don't need per repository rows. savings are what capping retention
+/// would save a month
+fn write_summary_json(
+    out: &mut dyn Write,
+    totals: &Totals,
+    metadata: &Metadata,
+) -> Result<(), Box<dyn Error>> {
+    let value = serde_json::json!({
+        "metadata": metadata,
+        "repositories": totals.repositories,
+        "hosted_images": totals.hosted_images,
+        "aggregate_image_size": totals.aggregate_image_size,
+        "monthly_cost": totals.monthly_cost,
+        "monthly_capped_cost": totals.monthly_capped_cost,
+        "monthly_savings": (totals.monthly_cost - totals.monthly_capped_cost).max(0.0),
+    });
+    writeln!(out, "{}", value)?;
+    Ok(())
+}
+
+/// a compact markdown block for bots commenting on pull requests: a heading
+/// naming the region and account, a one line total, and the most expensive
+/// repositories
+fn write_summary_markdown(
+    out: &mut dyn Write,
+    repos: &[Repo],
+    totals: &Totals,
+    metadata: &Metadata,
+) -> Result<(), IoError> {
+    match &metadata.account {
+        Some(account) => writeln!(out, "### ECR storage, {} ({})", metadata.region, account)?,
+        None => writeln!(out, "### ECR storage, {}", metadata.region)?,
+    }
+    writeln!(out)?;
+    writeln!(
+        out,
+        "**{}/month** (=> {} capped) across {} repositories and {} images",
+        format::cost(totals.monthly_cost),
+        format::cost(totals.monthly_capped_cost),
+        totals.repositories,
+        totals.hosted_images
+    )?;
+    let mut top = repos.iter().collect::<Vec<_>>();
+    top.sort_by(|a, b| b.monthly_cost().total_cmp(&a.monthly_cost()));
+    top.truncate(SUMMARY_MARKDOWN_TOP);
+    if top.is_empty() {
+        return Ok(());
+    }
+    writeln!(out)?;
+    writeln!(out, "| repository | images | monthly cost |")?;
+    writeln!(out, "| --- | ---: | ---: |")?;
+    for repo in top {
+        writeln!(
+            out,
+            "| {} | {} | {} |",
+            repo.name.replace('|', "\\|"),
+            repo.hosted_images,
+            format::cost(repo.monthly_cost())
+        )?;
+    }
+    Ok(())
+}
+
+/// runs a shell command, for `option` (`--pipe-through` or `--pager`),
+/// streaming what `write` renders to its stdin while it writes to ours and
+/// waiting for it to exit. a command exiting before reading everything isn't
+/// an error in itself, only its exit status is
+fn pipe_through(
+    option: &str,
+    command: &str,
+    write: impl FnOnce(&mut dyn Write) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut child = Command::new(shell)
+        .args([flag, command])
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("failed to run {} {}: {}", option, command, err))?;
+    let written = match child.stdin.take() {
+        Some(stdin) => {
+            let mut stdin = BufWriter::new(stdin);
+            write(&mut stdin).and_then(|()| Ok(stdin.flush()?))
+        }
+        None => Ok(()),
+    };
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(format!("{} {} failed with {}", option, command, status).into());
+    }
+    match written {
+        Err(err)
+            if err
+                .downcast_ref::<IoError>()
+                .is_some_and(|err| err.kind() == IoErrorKind::BrokenPipe) =>
+        {
+            Ok(())
+        }
+        written => written,
+    }
+}
+
+/// puts a rendered report on the system clipboard, falling back on stdout
+/// where there isn't one, e.g. over ssh
+fn copy_to_clipboard(report: &[u8]) -> Result<(), IoError> {
+    match clipboard::copy(report) {
+        Ok(()) => Ok(()),
+        Err(err) => {
+            WARNINGS.add("clipboard unavailable, written to stdout", err);
+            stdout().write_all(report)
+        }
+    }
+}
+
+/// repositories costing less than `threshold` a month rolled up into one
+/// `(other)` row after the rest. sizes, counts, and per repository details are
+/// summed, while what only makes sense of one repository (its latest push,
+/// size distribution, authors) is left out
+fn group_small(
+    repos: Vec<Repo>,
+    threshold: f64,
+) -> Vec<Repo> {
+    let (mut repos, small): (Vec<_>, Vec<_>) = repos
+        .into_iter()
+        .partition(|repo| repo.monthly_cost() >= threshold);
+    let mut small = small.into_iter();
+    let mut other = match small.next() {
+        Some(other) => other,
+        None => return repos,
+    };
+    other.name = "(other)".into();
+    other.raw_name = None;
+    other.arn = String::new();
+    other.last_pushed_at = None;
+    other.age_days = None;
+    other.previous_image_size = None;
+    other.size_stats = SizeStats::default();
+    other.authors = None;
+    other.average_layers = None;
+    for repo in small {
+        if other.account != repo.account {
+            other.account = None;
+        }
+        if repo.largest_image_size > other.largest_image_size {
+            other.largest_image = repo.largest_image;
+            other.largest_image_size = repo.largest_image_size;
+        }
+        other.latest_image_size += repo.latest_image_size;
+        other.reconciled_image_size += repo.reconciled_image_size;
+        other.aggregate_image_size += repo.aggregate_image_size;
+        other.artifact_size += repo.artifact_size;
+        other.recent_image_size += repo.recent_image_size;
+        other.reclaimable_image_size += repo.reclaimable_image_size;
+        other.billed_image_size += repo.billed_image_size;
+        other.hosted_images += repo.hosted_images;
+        other.total_images += repo.total_images;
+        other.untagged_images += repo.untagged_images;
+        other.latest_only &= repo.latest_only;
+        other.sampled |= repo.sampled;
+        other.tag_costs.extend(repo.tag_costs);
+        other.multi_arch.images += repo.multi_arch.images;
+        other.multi_arch.size += repo.multi_arch.size;
+        other.multi_arch.platforms = other.multi_arch.platforms.max(repo.multi_arch.platforms);
+        for (groups, merged) in [
+            (repo.tag_prefixes, &mut other.tag_prefixes),
+            (repo.architectures, &mut other.architectures),
+        ] {
+            for (name, group) in groups {
+                let entry = merged.entry(name).or_default();
+                entry.images += group.images;
+                entry.size += group.size;
+            }
+        }
+        other.images.extend(repo.images);
+        for (size, other_size) in repo.compared_caps.iter().zip(&mut other.compared_caps) {
+            *other_size += size;
+        }
+        other.broken_references.extend(repo.broken_references);
+    }
+    repos.push(other);
+    repos
+}
+
+/// lists repositories within `threshold` images of the per repository image
+/// quota, closest first
+fn warn_near_limit(
+    repos: &[Repo],
+    threshold: usize,
+    limit: usize,
+) -> Result<(), IoError> {
+    let mut near = repos
+        .iter()
+        .filter(|repo| repo.total_images + threshold >= limit)
+        .collect::<Vec<_>>();
+    if near.is_empty() {
+        return Ok(());
+    }
+    near.sort_by_key(|repo| Reverse(repo.total_images));
+    let mut writer = TabWriter::new(stderr());
+    writeln!(writer, "repositories near the {} image limit:", limit)?;
+    for repo in near {
+        writeln!(
+            writer,
+            "{}\t{} images\t{} remaining",
+            repo.name,
+            repo.total_images,
+            limit.saturating_sub(repo.total_images)
+        )?;
+    }
+    writer.flush()
+}
+
+/// `caller_account` is the account sts reported the credentials belong to,
+/// absent when scanning `--profiles`
+fn write_report(
+    mut out: &mut dyn Write,
+    repos: Vec<Repo>,
+    region: &Region,
+    opts: &Opts,
+    caller_account: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let format = opts.format.as_str();
+    let accounts = !opts.profiles.is_empty();
+    let mut fields = if opts.fields.is_empty() {
+        Field::defaults(accounts, opts.extra_columns)
+    } else {
+        opts.fields.clone()
+    };
+    if opts.explain && !fields.contains(&Field::CostFormula) {
+        fields.push(Field::CostFormula);
+    }
+    if opts.normalize_names.is_some() && !fields.contains(&Field::RawName) {
+        let after = fields.iter().position(|field| *field == Field::Name);
+        fields.insert(after.map_or(0, |index| index + 1), Field::RawName);
+    }
+    if opts.reconcile {
+        for field in [Field::ReconciledSize, Field::SizeDiscrepancy] {
+            if !fields.contains(&field) {
+                fields.push(field);
+            }
+        }
+    }
+    if opts.artifacts && !fields.contains(&Field::ArtifactSize) {
+        fields.push(Field::ArtifactSize);
+    }
+    if opts.stats {
+        for field in Field::STATS {
+            if !fields.contains(field) {
+                fields.push(*field);
+            }
+        }
+    }
+    if opts.percent && !fields.contains(&Field::PercentOfTotal) {
+        fields.push(Field::PercentOfTotal);
+    }
+    if opts.cost_breakdown {
+        for field in Field::COST_BREAKDOWN {
+            if !fields.contains(field) {
+                fields.push(*field);
+            }
+        }
+    }
+    if opts.sample.is_some() && !fields.contains(&Field::Sampled) {
+        fields.push(Field::Sampled);
+    }
+    if opts.warn_on_latest_only {
+        if !fields.contains(&Field::LatestOnly) {
+            fields.push(Field::LatestOnly);
+        }
+        for repo in repos.iter().filter(|repo| repo.latest_only) {
+            WARNINGS.add("repositories only ever tagged latest", repo.name.clone());
+        }
+    }
+    // shares need the grand total, so totals are computed before any rows
+    let totals = repos.iter().fold(Totals::default(), Totals::add);
+    let style = Style {
+        round_to: opts.round_to,
+        units: opts.units,
+        total_cost: totals.monthly_cost,
+    };
+    let repos = match opts.group_cost_threshold {
+        Some(threshold) => group_small(repos, threshold),
+        None => repos,
+    };
+    let pricing = opts.pricing();
+    // priced as monthly_cost() prices each repository, so the row at the
+    // current compression matches the totals
+    let billed_image_size = repos.iter().map(|repo| repo.billed_image_size).sum::<i64>();
+    // the table covers every mode, so it's printed once whichever one wrote
+    let sensitivity = (billed_image_size, totals.recent_image_size);
+    let written: Result<(), Box<dyn Error>> = 'report: {
+        if format == "kv" {
+            break 'report Ok(write_kv(out, &totals, region)?);
+        }
+        if opts.summary_only {
+            break 'report write_summary(out, &totals, format, region, opts, caller_account);
+        }
+        if format == "badge" {
+            break 'report write_badge(out, &totals, opts);
+        }
+        if format == "summary-json" {
+            break 'report write_summary_json(
+                out,
+                &totals,
+                &Metadata::new(region, opts, caller_account),
+            );
+        }
+        if format == "summary-markdown" {
+            let metadata = Metadata::new(region, opts, caller_account);
+            break 'report Ok(write_summary_markdown(out, &repos, &totals, &metadata)?);
+        }
+        if format == "parquet" {
+            let output = opts
+                .output
+                .as_ref()
+                .ok_or_else(|| UsageError("--format parquet requires --output".into()))?;
+            break 'report parquet_file::write(
+                &repos,
+                &Metadata::new(region, opts, caller_account),
+                output,
+            );
+        }
+        if format == "protobuf-schema" {
+            write!(out, "{}", protobuf::SCHEMA)?;
+            break 'report Ok(());
+        }
+        if format == "protobuf" {
+            let output = opts
+                .output
+                .as_ref()
+                .ok_or_else(|| UsageError("--format protobuf requires --output".into()))?;
+            let records = repos.iter().map(Record::from).collect::<Vec<_>>();
+            break 'report Ok(protobuf::write(
+                &records,
+                &Metadata::new(region, opts, caller_account),
+                &totals,
+                output,
+            )?);
+        }
+        if format == "excel" {
+            let output = opts
+                .output
+                .as_ref()
+                .ok_or_else(|| UsageError("--format excel requires --output".into()))?;
+            break 'report write_excel(&repos, &fields, &style, output);
+        }
+        if let (true, Some(dir)) = (opts.output_per_repo, &opts.output_dir) {
+            break 'report write_per_repo(out, &repos, format, pricing, dir);
+        }
+        if !opts.compare_cap.is_empty() {
+            break 'report Ok(write_compare_caps(
+                out,
+                &repos,
+                &opts.compare_cap,
+                format,
+                pricing,
+            )?);
+        }
+        if opts.tag_costs {
+            break 'report Ok(write_tag_costs(out, &repos, format, pricing)?);
+        }
+        if opts.multi_arch {
+            break 'report Ok(write_multi_arch(out, &repos, format)?);
+        }
+        if opts.check_references {
+            break 'report Ok(write_broken_references(out, &repos, format)?);
+        }
+        if opts.aggregate_by_tag_prefix {
+            break 'report Ok(write_tag_prefixes(out, &repos, format, pricing)?);
+        }
+        if opts.by_architecture {
+            break 'report Ok(write_architectures(out, &repos, format, pricing)?);
+        }
+        if let "json" | "gron" | "toml" = format {
+            let report = Report {
+                metadata: Metadata::new(region, opts, caller_account),
+                repositories: repos
+                    .iter()
+                    .map(|repo| {
+                        if opts.fields.is_empty() {
+                            let mut record = serde_json::to_value(Record::from(repo))?;
+                            if let Value::Object(record) = &mut record {
+                                if opts.units == Units::Millicents {
+                                    for field in [Field::Cost, Field::CappedCost] {
+                                        record.remove(field.key());
+                                        record.insert(
+                                            field.column(&style),
+                                            field.value(repo, &style).to_json(),
+                                        );
+                                    }
+                                }
+                                if opts.explain {
+                                    record.insert(
+                                        Field::CostFormula.key().into(),
+                                        repo.cost_formula().into(),
+                                    );
+                                }
+                                if opts.normalize_names.is_some() {
+                                    record.insert(
+                                        Field::RawName.key().into(),
+                                        Field::RawName.value(repo, &style).to_json(),
+                                    );
+                                }
+                                if opts.percent {
+                                    record.insert(
+                                        Field::PercentOfTotal.key().into(),
+                                        Field::PercentOfTotal.value(repo, &style).to_json(),
+                                    );
+                                }
+                                if opts.cost_breakdown {
+                                    for field in Field::COST_BREAKDOWN {
+                                        record.insert(
+                                            field.column(&style),
+                                            field.value(repo, &style).to_json(),
+                                        );
+                                    }
+                                }
+                                if opts.warn_on_latest_only {
+                                    record.insert(
+                                        Field::LatestOnly.key().into(),
+                                        Field::LatestOnly.value(repo, &style).to_json(),
+                                    );
+                                }
+                                if opts.sample.is_some() {
+                                    record.insert(
+                                        Field::Sampled.key().into(),
+                                        Field::Sampled.value(repo, &style).to_json(),
+                                    );
+                                }
+                            }
+                            Ok::<_, serde_json::Error>(record)
+                        } else {
+                            Ok(Value::Object(
+                                fields
+                                    .iter()
+                                    .map(|field| {
+                                        (field.column(&style), field.value(repo, &style).to_json())
+                                    })
+                                    .collect(),
+                            ))
+                        }
+                    })
+                    .collect::<Result<_, _>>()?,
+                totals,
+            };
+            let value = serde_json::to_value(report)?;
+            match format {
+                "json" => writeln!(out, "{}", serde_json::to_string_pretty(&value)?)?,
+                "toml" => toml_file::write(&mut out, &value)?,
+                _ => gron::write(&mut out, "json", &value)?,
+            }
+            break 'report Ok(());
+        }
+        match format {
+            "tsv" | "tsv-no-arrow" => {
+                // tsv-no-arrow is the minimal script mode, bare numbers separated by
+                // real tabs, without alignment, the forecast arrow, or a totals row
+                let plain = format == "tsv-no-arrow";
+                let mut writer: Box<dyn Write + '_> = if plain {
+                    Box::new(&mut *out)
+                } else {
+                    Box::new(TabWriter::new(&mut *out))
+                };
+                for repo in &repos {
+                    writeln!(
+                        writer,
+                        "{}",
+                        fields
+                            .iter()
+                            .map(|field| match (field, field.value(repo, &style)) {
+                                (_, Cell::Cost(cost)) if plain => format!("{:.2}", cost),
+                                (Field::CappedCost, cell) if !plain => format!("=> {}", cell),
+                                (Field::LastPushed, cell) => match repo.age_days {
+                                    Some(days) if opts.relative_dates && !plain => {
+                                        format::humanize_days(days)
+                                    }
+                                    _ => cell.to_string(),
+                                },
+                                (Field::Name, cell) => match opts.max_name_width {
+                                    Some(width) if !plain =>
+                                        format::truncate(&cell.to_string(), width),
+                                    _ => cell.to_string(),
+                                },
+                                (_, cell) => cell.to_string(),
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\t")
+                    )?;
+                }
+                if opts.subtotals && !plain && !opts.no_totals {
+                    let mut accounts = Vec::<(&str, Totals)>::new();
+                    for repo in &repos {
+                        let account = repo.account.as_deref().unwrap_or_default();
+                        match accounts.iter_mut().find(|(other, _)| *other == account) {
+                            Some((_, subtotal)) => *subtotal = mem::take(subtotal).add(repo),
+                            None => accounts.push((account, Totals::default().add(repo))),
+                        }
+                    }
+                    accounts.sort_by_key(|(account, _)| *account);
+                    for (account, subtotal) in &accounts {
+                        writeln!(
+                            writer,
+                            "{}",
+                            fields
+                                .iter()
+                                .map(|field| match field {
+                                    Field::Account => account.to_string(),
+                                    Field::Name => region.name().to_string(),
+                                    Field::Cost => style.cost(subtotal.monthly_cost).to_string(),
+                                    Field::CappedCost => {
+                                        format!("=> {}", style.cost(subtotal.monthly_capped_cost))
+                                    }
+                                    _ => String::new(),
+                                })
+                                .collect::<Vec<_>>()
+                                .join("\t")
+                        )?;
+                    }
+                }
+                if !plain && !opts.no_totals {
+                    writeln!(
+                        writer,
+                        "{}",
+                        fields
+                            .iter()
+                            .map(|field| match field {
+                                Field::Cost => style.cost(totals.monthly_cost).to_string(),
+                                Field::CappedCost => {
+                                    format!("=> {}", style.cost(totals.monthly_capped_cost))
+                                }
+                                _ => String::new(),
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\t")
+                    )?;
+                }
+                writer.flush()?;
+            }
+            "csv" => {
+                let mut csv = csv_writer(&mut *out);
+                csv.write_record(fields.iter().map(|field| field.column(&style)))?;
+                for repo in &repos {
+                    csv.write_record(
+                        fields
+                            .iter()
+                            .map(|field| field.value(repo, &style).to_string()),
+                    )?;
+                }
+                csv.flush()?;
+            }
+            "sql" => sql::write(&mut out, &opts.table, &fields, &style, &repos)?,
+            "tree" => tree::write(&mut *out, &repos)?,
+            "dot" => tree::write_dot(&mut *out, &repos)?,
+            "asciidoc" => asciidoc::write(&mut out, &fields, &style, &repos, &totals)?,
+            "html" => html::write(
+                &mut out,
+                &fields,
+                &style,
+                &repos,
+                &totals,
+                opts.embed_chart.then_some(opts.chart_top),
+            )?,
+            "logfmt" => logfmt::write(&mut out, &fields, &style, &repos, &totals)?,
+            "delta-csv" => {
+                // one (repository, metric, value) row per numeric field, which pivots
+                // cleanly and concatenates across runs
+                let metadata = Metadata::new(region, opts, caller_account);
+                let metrics = if opts.fields.is_empty() {
+                    Field::METRICS
+                } else {
+                    &fields[..]
+                };
+                let mut csv = csv_writer(&mut *out);
+                csv.write_record(["region", "account", "repository", "metric", "value"])?;
+                for repo in &repos {
+                    let account = repo
+                        .account
+                        .as_ref()
+                        .or(metadata.account.as_ref())
+                        .cloned()
+                        .unwrap_or_default();
+                    for field in metrics {
+                        let value = match field.value(repo, &style) {
+                            Cell::Text(_) => continue,
+                            cell => match cell.to_json() {
+                                Value::Null => String::new(),
+                                value => value.to_string(),
+                            },
+                        };
+                        csv.write_record(&[
+                            metadata.region.clone(),
+                            account.clone(),
+                            repo.name.clone(),
+                            field.column(&style),
+                            value,
+                        ])?;
+                    }
+                }
+                csv.flush()?;
+            }
+            "openmetrics" => openmetrics::write(&mut out, &repos)?,
+            "template" => {
+                let template = opts
+                    .template
+                    .as_ref()
+                    .ok_or_else(|| UsageError("--format template requires --template".into()))?;
+                for repo in &repos {
+                    writeln!(out, "{}", template.render(repo, &style))?;
+                }
+            }
+            "influx" => {
+                let timestamp = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+                for repo in &repos {
+                    writeln!(
+                        out,
+                        "ecr_cost,{}repository={} monthly_cost={},monthly_capped_cost={},latest_image_size={}i,aggregate_image_size={}i,hosted_images={}i {}",
+                        repo.account
+                            .as_ref()
+                            .map(|account| format!("account={},", influx_tag(account)))
+                            .unwrap_or_default(),
+                        influx_tag(&repo.name),
+                        repo.monthly_cost(),
+                        repo.monthly_capped_cost(),
+                        repo.latest_image_size,
+                        repo.aggregate_image_size,
+                        repo.hosted_images,
+                        timestamp
+                    )?;
+                }
+            }
+            other => break 'report Err(UsageError(format!("unknown format {}", other)).into()),
+        }
+        Ok(())
+    };
+    written?;
+    if opts.sensitivity {
+        // keep machine readable formats parseable by reporting on stderr
+        let mut writer: TabWriter<Box<dyn Write + '_>> = TabWriter::new(if format == "tsv" {
+            Box::new(out)
+        } else {
+            Box::new(stderr())
+        });
+        writeln!(writer)?;
+        writeln!(writer, "compression\tmonthly\tcapped")?;
+        for compression in Repo::SENSITIVITY.iter() {
+            writeln!(
+                writer,
+                "{:.2}\t{}\t=> {}",
+                compression,
+                format::cost(pricing.storage_cost(sensitivity.0, *compression)),
+                format::cost(pricing.storage_cost(sensitivity.1, *compression))
+            )?;
+        }
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+fn main() {
+    #[cfg(all(
+        unix,
+        not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))
+    ))]
+    if env::var_os(clipboard::HOLDER).is_some() {
+        process::exit(match clipboard::hold() {
+            Ok(()) => 0,
+            Err(_) => EXIT_ERROR,
+        });
+    }
+    env_logger::init();
+    let opts = Opts::from_iter_safe(env::args_os()).unwrap_or_else(|err| match err.kind {
+        ErrorKind::HelpDisplayed | ErrorKind::VersionDisplayed => err.exit(),
+        _ => {
+            match ErrorFormat::from_args() {
+                ErrorFormat::Text => eprintln!("{}", err.message),
+                json => json.report("usage", &err.message, EXIT_USAGE),
+            }
+            process::exit(EXIT_USAGE)
+        }
+    });
+    let error_format = opts.error_format;
+    process::exit(match run(opts) {
+        Ok(status) => status,
+        Err(err) => {
+            let status = if err.is::<UsageError>() {
+                EXIT_USAGE
+            } else {
+                EXIT_ERROR
+            };
+            error_format.report(error_kind(&*err), &err.to_string(), status);
+            status
+        }
+    })
+}
+
+/// who the default credentials belong to, failing when sts rejects them so
+/// runs with expired or mistaken credentials stop before scanning
+fn caller_identity(
+    proxy: Option<&str>,
+    region: &Region,
+) -> Result<GetCallerIdentityResponse, Box<dyn Error>> {
+    TIMINGS.api_call();
+    StsClient::new_with(
+        http::client(proxy)?,
+        DefaultCredentialsProvider::new()?,
+        region.clone(),
+    )
+    .get_caller_identity(GetCallerIdentityRequest {})
+    .sync()
+    .map_err(|err| IdentityError(err).into())
+}
+
+/// scans and reports, returning the exit status
+fn run(opts: Opts) -> Result<i32, Box<dyn Error>> {
+    if opts.emit_schema {
+        print!("{}", REPORT_SCHEMA);
+        return Ok(0);
+    }
+    if let (true, Some(compression)) = (opts.no_compression, opts.compression) {
+        WARNINGS.add(
+            "ignored options",
+            format!("--no-compression overrides --compression {}", compression),
+        );
+    }
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::Relaxed) {
+            // a second ctrl-c abandons the run
+            process::exit(130);
+        }
+        eprintln!("Interrupted, finishing the current repository");
+    })?;
+    if let (Some(Some(_)), false) = (&opts.account_alias, opts.profiles.is_empty()) {
+        return Err(UsageError(
+            "--account-alias only takes a value when scanning a single account, pass it \
+             alone to look up each profile's alias"
+                .into(),
+        )
+        .into());
+    }
+    THROTTLE.set_rate(opts.rate);
+    CSV_DELIMITER.store(opts.delimiter, Ordering::Relaxed);
+    if let Some(retries) = opts.total_retries {
+        THROTTLE.set_retry_budget(retries);
+    }
+    if opts.throttle_on_error {
+        if opts.rate > 0.0 {
+            THROTTLE.set_adaptive();
+        } else {
+            WARNINGS.add(
+                "ignored options",
+                "--throttle-on-error needs a limited --rate to adapt".into(),
+            );
+        }
+    }
+    if let Some(days) = opts.cap_days {
+        let now = opts.as_of.unwrap_or_else(|| Utc::now().naive_utc());
+        let period_start = NaiveDateTime::new(
+            period_start(now.date(), opts.period_start_day),
+            NaiveTime::MIN,
+        );
+        if period_start
+            .checked_sub_signed(chrono::Duration::days(days.into()))
+            .is_none()
+        {
+            return Err(UsageError(format!(
+                "--cap-days {} reaches back further than dates can be represented",
+                days
+            ))
+            .into());
+        }
+    }
+    let started = Instant::now();
+    let region = Region::default();
+    let scan = Scan {
+        registry_id: opts.registry_id.clone(),
+        proxy: opts.proxy.clone(),
+        tag_status: opts.tag_status,
+        tag_mutability: if opts.only_mutable {
+            Some("MUTABLE")
+        } else if opts.only_immutable {
+            Some("IMMUTABLE")
+        } else {
+            None
+        },
+        repositories: Selection {
+            include: opts
+                .include_file
+                .as_deref()
+                .map(read_names)
+                .transpose()?
+                .unwrap_or_default(),
+            exclude: opts
+                .exclude_file
+                .as_deref()
+                .map(read_names)
+                .transpose()?
+                .unwrap_or_default(),
+        },
+        pricing: opts.pricing(),
+        min_size: opts.min_size.unwrap_or_default(),
+        as_of: opts.as_of,
+        period_start_day: opts.period_start_day,
+        sample: opts.sample,
+        ignore_media_types: opts.ignore_media_types.clone(),
+        cost_model: opts.cost_model,
+        account_aliases: opts.account_alias.is_some(),
+        retention: Retention {
+            cap: match opts.cap_days {
+                Some(days) => Cap::Days(days),
+                None => Cap::Images(opts.cap.unwrap_or(2)),
+            },
+            protect_tags: opts.protect_tags.clone(),
+        },
+        details: Details {
+            tag_costs: opts.tag_costs,
+            authors: opts.uses(Field::Authors),
+            layers: opts.uses(Field::Layers),
+            multi_arch: opts.multi_arch,
+            architectures: opts.by_architecture,
+            tag_prefix_separator: if opts.aggregate_by_tag_prefix {
+                Some(opts.tag_prefix_separator.clone())
+            } else {
+                None
+            },
+            images: opts.output_per_repo,
+            reconcile: opts.reconcile,
+            artifacts: opts.artifacts,
+            references: opts.check_references,
+            stats: opts.stats || Field::STATS.iter().any(|field| opts.uses(*field)),
+            compare_caps: opts.compare_cap.clone(),
+        },
+    };
+    // sts is only required when the account is asked for, so roles holding
+    // just ecr:Describe* can still scan. otherwise the identity only fills in
+    // the report metadata's account, which is left out when sts refuses
+    let identity = if !opts.profiles.is_empty() {
+        None
+    } else if opts.whoami
+        || opts.show_identity
+        || opts.uses(Field::Account)
+        || matches!(opts.account_alias, Some(None))
+    {
+        Some(caller_identity(scan.proxy.as_deref(), &region)?)
+    } else {
+        match caller_identity(scan.proxy.as_deref(), &region) {
+            Ok(identity) => Some(identity),
+            Err(err) => {
+                WARNINGS.add(
+                    "caller identity unavailable, reported without an account",
+                    err.to_string(),
+                );
+                None
+            }
+        }
+    };
+    let caller_account = identity
+        .as_ref()
+        .and_then(|identity| identity.account.as_deref());
+    let mut repos = if opts.profiles.is_empty() {
+        if let Some(identity) = &identity {
+            let account = identity.account.as_deref().unwrap_or_default();
+            let arn = identity.arn.as_deref().unwrap_or_default();
+            if opts.whoami {
+                println!("account\t{}", account);
+                println!("arn\t{}", arn);
+                return Ok(0);
+            }
+            if opts.show_identity {
+                eprintln!("Scanning as {} (account {})", arn, account);
+            }
+        }
+        let ecr = EcrClient::new_with(
+            http::client(scan.proxy.as_deref())?,
+            DefaultCredentialsProvider::new()?,
+            region.clone(),
+        );
+        if opts.repositories_only {
+            let repositories = load_all_repositories(&ecr, scan.registry_id.clone(), None)?
+                .into_iter()
+                .filter(|repo| scan.selected(repo))
+                .collect::<Vec<_>>();
+            let mut out = BufWriter::new(stdout().lock());
+            write_repositories(&mut out, &repositories, &opts.format)?;
+            out.flush()?;
+            return Ok(0);
+        }
+        if opts.count_only {
+            let mut out = BufWriter::new(stdout().lock());
+            write_counts(&mut out, &count(&ecr, &scan)?)?;
+            out.flush()?;
+            if !opts.quiet {
+                WARNINGS.report();
+            }
+            return Ok(0);
+        }
+        if let Some(path) = &opts.image_digests_file {
+            let mut repositories = Vec::<(String, Vec<String>)>::new();
+            for (repository, digest) in read_image_digests(path, opts.skip_malformed)? {
+                match repositories
+                    .iter_mut()
+                    .find(|(name, _)| *name == repository)
+                {
+                    Some((_, digests)) => digests.push(digest),
+                    None => repositories.push((repository, vec![digest])),
+                }
+            }
+            let repositories = repositories
+                .into_iter()
+                .map(|(repository, digests)| {
+                    let images =
+                        digest_images(&ecr, scan.registry_id.clone(), &repository, &digests)?;
+                    Ok((repository, images))
+                })
+                .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+            let mut out = BufWriter::new(stdout().lock());
+            write_digest_images(&mut out, &repositories, &opts.format, scan.pricing)?;
+            out.flush()?;
+            if !opts.quiet {
+                WARNINGS.report();
+            }
+            return Ok(0);
+        }
+        if let (Some(repo), Some(tag)) = (&opts.repo, &opts.image_tag) {
+            let details = tagged_image(&ecr, scan.registry_id.clone(), repo, tag)?;
+            write_image(&mut stdout(), &details, scan.pricing)?;
+            return Ok(0);
+        }
+        repos(&ecr, &region, &scan)?
+    } else {
+        all_profile_repos(&opts.profiles, &region, &scan, opts.concurrency)
+    };
+    if let Some(pattern) = &opts.normalize_names {
+        for repo in &mut repos {
+            let name = strip_prefix(pattern, &repo.name).to_string();
+            repo.raw_name = Some(mem::replace(&mut repo.name, name));
+        }
+    }
+    repos.sort_by_key(|repo| Reverse(repo.latest_image_size));
+    if interrupted() {
+        eprintln!(
+            "Results are partial, only {} repositories were scanned before the interrupt",
+            repos.len()
+        );
+    }
+    let formatting = Instant::now();
+    if opts.estimate_deletion_savings {
+        let reclaimable = repos
+            .iter()
+            .map(|repo| repo.reclaimable_image_size)
+            .sum::<i64>();
+        eprintln!(
+            "Deleting untagged and beyond cap images would reclaim ~{} and ~{}/month",
+            format::bytes(reclaimable),
+            format::cost(opts.pricing().cost(reclaimable))
+        );
+    }
+    if let Some(threshold) = opts.near_limit {
+        warn_near_limit(&repos, threshold, opts.image_limit)?;
+    }
+    let violations = opts
+        .fail_on_untagged
+        .map(|threshold| {
+            repos
+                .iter()
+                .filter(|repo| repo.untagged_images > threshold)
+                .map(|repo| (repo.name.clone(), repo.untagged_images))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let monthly_cost = repos
+        .iter()
+        .fold(Totals::default(), Totals::add)
+        .monthly_cost;
+    // the pager runs as a child rather than taking over this process, so the
+    // exit status and the warnings below follow once it's quit
+    let pager = (opts.pager && opts.format == "tsv" && stdout().is_terminal())
+        .then(|| env::var("PAGER").unwrap_or_else(|_| "less".into()))
+        .filter(|pager| !pager.is_empty());
+    if opts.clipboard {
+        let mut report = Vec::new();
+        write_report(&mut report, repos, &region, &opts, caller_account)?;
+        copy_to_clipboard(&report)?;
+    } else if let Some(command) = &opts.pipe_through {
+        pipe_through("--pipe-through", command, |out| {
+            write_report(out, repos, &region, &opts, caller_account)
+        })?;
+    } else if let Some(pager) = &pager {
+        pipe_through("--pager", pager, |out| {
+            write_report(out, repos, &region, &opts, caller_account)
+        })?;
+    } else {
+        // rows are written one at a time, so buffering saves a syscall per row
+        let mut out = BufWriter::new(stdout().lock());
+        write_report(&mut out, repos, &region, &opts, caller_account)?;
+        out.flush()?;
+    }
+    Timings::record(&TIMINGS.formatting, formatting);
+    if opts.timings {
+        TIMINGS.report(started);
+    }
+    if !opts.quiet {
+        WARNINGS.report();
+    }
+    let mut status = 0;
+    if let Some(budget) = opts.budget.filter(|budget| monthly_cost > *budget) {
+        eprintln!(
+            "monthly cost {} exceeds the {} budget",
+            format::cost(monthly_cost),
+            format::cost(budget)
+        );
+        status = EXIT_BUDGET;
+    }
+    if !violations.is_empty() {
+        for (name, untagged_images) in violations {
+            eprintln!("{} has {} untagged images", name, untagged_images);
+        }
+        status = cmp::max(status, EXIT_POLICY);
+    }
+
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusoto_ecr::{DescribeImagesResponse, DescribeRepositoriesResponse};
+
+    /// canned ecr responses, paginated by token: the first page is requested
+    /// without one, page `n` with the token `n`
+    #[derive(Default)]
+    struct StubEcr {
+        repositories: Vec<Vec<Repository>>,
+        /// pages of each repository's images, repositories missing here being
+        /// reported not found
+        images: HashMap<String, Vec<Vec<ImageDetail>>>,
+        /// tokens describe_images was listed with, in order
+        image_tokens: Mutex<Vec<Option<String>>>,
+    }
+
+    impl StubEcr {
+        /// a registry of a single repository, app, hosting the given pages
+        fn new(pages: Vec<Vec<ImageDetail>>) -> Self {
+            StubEcr {
+                repositories: vec![vec![repository("app")]],
+                images: vec![("app".to_string(), pages)].into_iter().collect(),
+                ..StubEcr::default()
+            }
+        }
+    }
+
+    /// the page a token requests and the token of the one after it
+    fn page<T: Clone>(
+        pages: &[Vec<T>],
+        token: Option<String>,
+    ) -> (Vec<T>, Option<String>) {
+        let index = token.map_or(0, |token| token.parse().unwrap());
+        let next_token = Some(index + 1)
+            .filter(|next| *next < pages.len())
+            .map(|next| next.to_string());
+        (pages.get(index).cloned().unwrap_or_default(), next_token)
+    }
+
+    /// operations the scan never makes, failing a test that reaches one with
+    /// an error naming it
+    macro_rules! unsupported {
+        ($($method:ident: $request:ident, $response:ident, $error:ident;)*) => {
+            $(
+                fn $method(
+                    &self,
+                    _: rusoto_ecr::$request,
+                ) -> RusotoFuture<rusoto_ecr::$response, rusoto_ecr::$error> {
+                    RusotoFuture::from(Err(RusotoError::Validation(format!(
+                        "StubEcr does not support {}",
+                        stringify!($method)
+                    ))))
+                }
+            )*
+        };
+    }
+
+    impl Ecr for StubEcr {
+        fn describe_repositories(
+            &self,
+            input: DescribeRepositoriesRequest,
+        ) -> RusotoFuture<DescribeRepositoriesResponse, DescribeRepositoriesError> {
+            let (repositories, next_token) = page(&self.repositories, input.next_token);
+            RusotoFuture::from(Ok(DescribeRepositoriesResponse {
+                repositories: Some(repositories),
+                next_token,
+            }))
+        }
+
+        fn describe_images(
+            &self,
+            input: DescribeImagesRequest,
+        ) -> RusotoFuture<DescribeImagesResponse, DescribeImagesError> {
+            let pages = match self.images.get(&input.repository_name) {
+                Some(pages) => pages,
+                None => {
+                    return RusotoFuture::from(Err(RusotoError::Service(
+                        DescribeImagesError::RepositoryNotFound(input.repository_name),
+                    )))
+                }
+            };
+            if let Some(ids) = input.image_ids {
+                // as with ecr, a batch naming a missing image fails as a whole
+                let found = ids
+                    .iter()
+                    .map(|id| {
+                        pages.iter().flatten().find(|details| {
+                            id.image_digest.is_some() && id.image_digest == details.image_digest
+                                || id.image_tag.as_ref().is_some_and(|tag| {
+                                    details
+                                        .image_tags
+                                        .iter()
+                                        .flatten()
+                                        .any(|other| other == tag)
+                                })
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>();
+                return RusotoFuture::from(match found {
+                    Some(found) => Ok(DescribeImagesResponse {
+                        image_details: Some(found.into_iter().cloned().collect()),
+                        next_token: None,
+                    }),
+                    None => Err(RusotoError::Service(DescribeImagesError::ImageNotFound(
+                        "requested image not found".into(),
+                    ))),
+                });
+            }
+            self.image_tokens
+                .lock()
+                .unwrap()
+                .push(input.next_token.clone());
+            let (image_details, next_token) = page(pages, input.next_token);
+            RusotoFuture::from(Ok(DescribeImagesResponse {
+                image_details: Some(image_details),
+                next_token,
+            }))
+        }
+
+        unsupported! {
+            batch_check_layer_availability: BatchCheckLayerAvailabilityRequest,
+                BatchCheckLayerAvailabilityResponse, BatchCheckLayerAvailabilityError;
+            batch_delete_image: BatchDeleteImageRequest, BatchDeleteImageResponse,
+                BatchDeleteImageError;
+            batch_get_image: BatchGetImageRequest, BatchGetImageResponse, BatchGetImageError;
+            complete_layer_upload: CompleteLayerUploadRequest, CompleteLayerUploadResponse,
+                CompleteLayerUploadError;
+            create_repository: CreateRepositoryRequest, CreateRepositoryResponse,
+                CreateRepositoryError;
+            delete_lifecycle_policy: DeleteLifecyclePolicyRequest, DeleteLifecyclePolicyResponse,
+                DeleteLifecyclePolicyError;
+            delete_repository: DeleteRepositoryRequest, DeleteRepositoryResponse,
+                DeleteRepositoryError;
+            delete_repository_policy: DeleteRepositoryPolicyRequest,
+                DeleteRepositoryPolicyResponse, DeleteRepositoryPolicyError;
+            get_authorization_token: GetAuthorizationTokenRequest, GetAuthorizationTokenResponse,
+                GetAuthorizationTokenError;
+            get_download_url_for_layer: GetDownloadUrlForLayerRequest,
+                GetDownloadUrlForLayerResponse, GetDownloadUrlForLayerError;
+            get_lifecycle_policy: GetLifecyclePolicyRequest, GetLifecyclePolicyResponse,
+                GetLifecyclePolicyError;
+            get_lifecycle_policy_preview: GetLifecyclePolicyPreviewRequest,
+                GetLifecyclePolicyPreviewResponse, GetLifecyclePolicyPreviewError;
+            get_repository_policy: GetRepositoryPolicyRequest, GetRepositoryPolicyResponse,
+                GetRepositoryPolicyError;
+            initiate_layer_upload: InitiateLayerUploadRequest, InitiateLayerUploadResponse,
+                InitiateLayerUploadError;
+            list_images: ListImagesRequest, ListImagesResponse, ListImagesError;
+            list_tags_for_resource: ListTagsForResourceRequest, ListTagsForResourceResponse,
+                ListTagsForResourceError;
+            put_image: PutImageRequest, PutImageResponse, PutImageError;
+            put_image_tag_mutability: PutImageTagMutabilityRequest, PutImageTagMutabilityResponse,
+                PutImageTagMutabilityError;
+            put_lifecycle_policy: PutLifecyclePolicyRequest, PutLifecyclePolicyResponse,
+                PutLifecyclePolicyError;
+            set_repository_policy: SetRepositoryPolicyRequest, SetRepositoryPolicyResponse,
+                SetRepositoryPolicyError;
+            start_lifecycle_policy_preview: StartLifecyclePolicyPreviewRequest,
+                StartLifecyclePolicyPreviewResponse, StartLifecyclePolicyPreviewError;
+            tag_resource: TagResourceRequest, TagResourceResponse, TagResourceError;
+            untag_resource: UntagResourceRequest, UntagResourceResponse, UntagResourceError;
+            upload_layer_part: UploadLayerPartRequest, UploadLayerPartResponse,
+                UploadLayerPartError;
+        }
+    }
+
+    /// mid march 2024, so images pushed before the 1st are hosted
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 3, 15)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .unwrap()
+    }
+
+    fn scan() -> Scan {
+        Scan {
+            registry_id: None,
+            proxy: None,
+            tag_status: TagStatus::Any,
+            repositories: Selection {
+                include: Vec::new(),
+                exclude: Vec::new(),
+            },
+            tag_mutability: None,
+            retention: Retention {
+                cap: Cap::Images(2),
+                protect_tags: Vec::new(),
+            },
+            pricing: Pricing {
+                gb_base: 1024,
+                compression: 1.0,
+            },
+            min_size: 0,
+            as_of: Some(now()),
+            period_start_day: 1,
+            sample: None,
+            ignore_media_types: Vec::new(),
+            cost_model: Model::Snapshot,
+            account_aliases: false,
+            details: Details::default(),
+        }
+    }
+
+    fn repository(name: &str) -> Repository {
+        Repository {
+            repository_name: Some(name.into()),
+            repository_arn: Some(format!(
+                "arn:aws:ecr:us-east-1:123456789012:repository/{}",
+                name
+            )),
+            ..Repository::default()
+        }
+    }
+
+    /// an image pushed a number of days before `now`, untagged without tags
+    fn image(
+        digest: &str,
+        tags: &[&str],
+        size: i64,
+        days: i64,
+    ) -> ImageDetail {
+        ImageDetail {
+            image_digest: Some(digest.into()),
+            image_tags: Some(tags.iter().map(|tag| tag.to_string()).collect())
+                .filter(|tags: &Vec<_>| !tags.is_empty()),
+            image_size_in_bytes: Some(size),
+            image_pushed_at: Some(
+                (now() - chrono::Duration::days(days)).and_utc().timestamp() as f64
+            ),
+            ..ImageDetail::default()
+        }
+    }
+
+    fn close(
+        actual: f64,
+        expected: f64,
+    ) -> bool {
+        (actual - expected).abs() < 1e-9
+    }
+
+    /// a scanned repository hosting a 1 GiB `v1` and a 2 GiB `v2`, both pushed
+    /// last month, for the output formats' tests
+    pub(crate) fn repo(name: &str) -> Repo {
+        let ecr = StubEcr {
+            repositories: vec![vec![repository(name)]],
+            images: vec![(
+                name.to_string(),
+                vec![vec![
+                    image("sha256:a", &["v1"], 1 << 30, 40),
+                    image("sha256:b", &["v2"], 2 << 30, 20),
+                ]],
+            )]
+            .into_iter()
+            .collect(),
+            ..StubEcr::default()
+        };
+        repos(&ecr, &Region::UsEast1, &scan()).unwrap().remove(0)
+    }
+
+    #[test]
+    fn images_are_followed_across_pages() {
+        let ecr = StubEcr::new(vec![
+            vec![image("sha256:a", &["v1"], 1, 30)],
+            vec![
+                image("sha256:b", &["v2"], 2, 30),
+                image("sha256:c", &[], 4, 30),
+            ],
+            vec![image("sha256:d", &["v3"], 8, 30)],
+        ]);
+        let mut sizes = Vec::new();
+        let listed = each_image(&ecr, None, "app".into(), TagStatus::Any, None, |details| {
+            sizes.push(details.image_size_in_bytes.unwrap_or_default())
+        })
+        .unwrap();
+        assert_eq!(listed, 4);
+        assert_eq!(sizes, [1, 2, 4, 8]);
+        assert_eq!(
+            *ecr.image_tokens.lock().unwrap(),
+            [None, Some("1".to_string()), Some("2".to_string())]
+        );
+    }
+
+    #[test]
+    fn repositories_are_followed_across_pages() {
+        let names = ["a", "b", "c"];
+        let ecr = StubEcr {
+            repositories: vec![
+                vec![repository("a")],
+                vec![repository("b"), repository("c")],
+            ],
+            images: names
+                .iter()
+                .map(|name| {
+                    (
+                        name.to_string(),
+                        vec![vec![image("sha256:a", &["v1"], 1, 30)]],
+                    )
+                })
+                .collect(),
+            ..StubEcr::default()
+        };
+        let scanned = repos(&ecr, &Region::UsEast1, &scan()).unwrap();
+        assert_eq!(
+            scanned
+                .iter()
+                .map(|repo| repo.name.as_str())
+                .collect::<Vec<_>>(),
+            names
+        );
+    }
+
+    #[test]
+    fn selection_includes_then_excludes() {
+        let selection = Selection {
+            include: vec!["team/*".into()],
+            exclude: vec!["team/old-*".into()],
+        };
+        assert!(selection.selected("team/api"));
+        assert!(!selection.selected("team/old-api"));
+        assert!(!selection.selected("other/web"));
+        let everything = Selection {
+            include: Vec::new(),
+            exclude: vec!["scratch".into()],
+        };
+        assert!(everything.selected("other/web"));
+        assert!(!everything.selected("scratch"));
+    }
+
+    #[test]
+    fn scans_only_selected_repositories() {
+        let names = ["team/api", "team/old-api", "other/web"];
+        let ecr = StubEcr {
+            repositories: vec![names.iter().map(|name| repository(name)).collect()],
+            images: names
+                .iter()
+                .map(|name| {
+                    (
+                        name.to_string(),
+                        vec![vec![image("sha256:a", &["v1"], 1, 30)]],
+                    )
+                })
+                .collect(),
+            ..StubEcr::default()
+        };
+        let mut scan = scan();
+        scan.repositories = Selection {
+            include: vec!["team/*".into()],
+            exclude: vec!["team/old-*".into()],
+        };
+        let scanned = repos(&ecr, &Region::UsEast1, &scan).unwrap();
+        assert_eq!(
+            scanned
+                .iter()
+                .map(|repo| repo.name.as_str())
+                .collect::<Vec<_>>(),
+            ["team/api"]
+        );
+    }
+
+    #[test]
+    fn pricing_costs_fixed_sizes() {
+        let gib = Pricing {
+            gb_base: 1024,
+            compression: 1.0,
+        };
+        assert!(close(gib.cost(0), 0.0));
+        assert!(close(gib.cost(1 << 30), RATE));
+        assert!(close(gib.cost(5 << 30), 5.0 * RATE));
+        let gb = Pricing {
+            gb_base: 1000,
+            compression: 0.5,
+        };
+        assert!(close(gb.cost(4_000_000_000), 2.0 * RATE));
+    }
+
+    #[test]
+    fn monthly_costs_bill_hosted_images() {
+        let ecr = StubEcr::new(vec![vec![
+            image("sha256:a", &["v1"], 2 << 30, 40),
+            image("sha256:b", &["v2"], 1 << 30, 20),
+            // pushed this month, which the snapshot model doesn't bill
+            image("sha256:c", &["v3"], 1 << 30, 5),
+        ]]);
+        let mut scan = scan();
+        scan.retention.cap = Cap::Images(1);
+        let scanned = repos(&ecr, &Region::UsEast1, &scan).unwrap();
+        assert_eq!(scanned[0].billed_image_size, 3 << 30);
+        assert!(close(scanned[0].monthly_cost(), 3.0 * RATE));
+        assert!(close(scanned[0].monthly_capped_cost(), RATE));
+        scan.pricing.compression = Repo::COMPRESSION;
+        let scanned = repos(&ecr, &Region::UsEast1, &scan).unwrap();
+        assert!(close(
+            scanned[0].monthly_cost(),
+            3.0 * Repo::COMPRESSION * RATE
+        ));
+    }
+
+    #[test]
+    fn digests_repeated_across_pages_count_once() {
+        let ecr = StubEcr::new(vec![
+            vec![
+                image("sha256:a", &["v1"], 1, 30),
+                image("sha256:b", &["v2"], 2, 30),
+            ],
+            vec![
+                image("sha256:b", &["v2"], 2, 30),
+                image("sha256:c", &["v3"], 4, 30),
+            ],
+        ]);
+        let listed = each_image(&ecr, None, "app".into(), TagStatus::Any, None, |_| ()).unwrap();
+        assert_eq!(listed, 3);
+        let scanned = repos(&ecr, &Region::UsEast1, &scan()).unwrap();
+        assert_eq!(scanned[0].total_images, 3);
+        assert_eq!(scanned[0].aggregate_image_size, 7);
+    }
+
+    #[test]
+    fn empty_pages_with_a_token_are_followed() {
+        let ecr = StubEcr {
+            repositories: vec![vec![], vec![repository("app")]],
+            images: vec![(
+                "app".to_string(),
+                vec![
+                    vec![image("sha256:a", &["v1"], 1, 30)],
+                    vec![],
+                    vec![image("sha256:b", &["v2"], 2, 30)],
+                ],
+            )]
+            .into_iter()
+            .collect(),
+            ..StubEcr::default()
+        };
+        let scanned = repos(&ecr, &Region::UsEast1, &scan()).unwrap();
+        assert_eq!(scanned.len(), 1);
+        assert_eq!(scanned[0].total_images, 2);
+        assert_eq!(ecr.image_tokens.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn equal_push_times_fall_back_on_the_digest() {
+        let pushed = |listed: Vec<ImageDetail>, images| {
+            let mut scan = scan();
+            scan.retention.cap = Cap::Images(1);
+            scan.details.images = images;
+            repos(&StubEcr::new(vec![listed]), &Region::UsEast1, &scan)
+                .unwrap()
+                .remove(0)
+        };
+        let (a, b) = (
+            image("sha256:a", &["a"], 1, 30),
+            image("sha256:b", &["b"], 2, 30),
+        );
+        for images in [false, true] {
+            for listed in [vec![a.clone(), b.clone()], vec![b.clone(), a.clone()]] {
+                let repo = pushed(listed, images);
+                assert_eq!(repo.latest_image_size, 1);
+                assert_eq!(repo.previous_image_size, Some(2));
+                assert_eq!(repo.recent_image_size, 1);
+            }
+        }
+        let repo = pushed(vec![b, a], true);
+        assert_eq!(
+            repo.images
+                .iter()
+                .map(|image| image.digest.as_str())
+                .collect::<Vec<_>>(),
+            ["sha256:a", "sha256:b"]
+        );
+    }
+
+    #[test]
+    fn unrepresentable_push_times_fall_back_on_the_epoch() {
+        let pushed = |seconds| {
+            pushed_at(&ImageDetail {
+                image_pushed_at: seconds,
+                ..ImageDetail::default()
+            })
+        };
+        let epoch = NaiveDateTime::default();
+        for seconds in [
+            None,
+            Some(f64::NAN),
+            Some(f64::INFINITY),
+            Some(f64::NEG_INFINITY),
+        ] {
+            assert_eq!(pushed(seconds), epoch);
+        }
+        assert_eq!(pushed(Some(1e300)), epoch);
+        assert_eq!(pushed(Some(-1e300)), epoch);
+        assert_eq!(
+            pushed(Some(1_700_000_000.9)).and_utc().timestamp(),
+            1_700_000_000
+        );
+        assert_eq!(pushed(Some(-1.5)).and_utc().timestamp(), -1);
+    }
+
+    #[test]
+    fn repositories_deleted_during_the_scan_are_skipped() {
+        let ecr = StubEcr {
+            repositories: vec![vec![repository("deleted"), repository("app")]],
+            ..StubEcr::new(vec![vec![image("sha256:a", &["v1"], 1, 30)]])
+        };
+        let scanned = repos(&ecr, &Region::UsEast1, &scan()).unwrap();
+        assert_eq!(
+            scanned
+                .iter()
+                .map(|repo| repo.name.as_str())
+                .collect::<Vec<_>>(),
+            ["app"]
+        );
+        assert!(digest_images(&ecr, None, "deleted", &["sha256:a".into()])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn missing_images_are_reported_or_skipped() {
+        let ecr = StubEcr::new(vec![vec![
+            image("sha256:a", &["v1"], 1, 30),
+            image("sha256:b", &["v2"], 2, 30),
+        ]]);
+        assert_eq!(
+            tagged_image(&ecr, None, "app", "v2")
+                .unwrap()
+                .image_digest
+                .as_deref(),
+            Some("sha256:b")
+        );
+        assert_eq!(
+            tagged_image(&ecr, None, "app", "v3")
+                .unwrap_err()
+                .to_string(),
+            "no image tagged v3 in repository app"
+        );
+        let found = digest_images(
+            &ecr,
+            None,
+            "app",
+            &["sha256:a".into(), "sha256:gone".into(), "sha256:b".into()],
+        )
+        .unwrap();
+        assert_eq!(
+            found
+                .iter()
+                .map(|details| details.image_digest.as_deref().unwrap_or_default())
+                .collect::<Vec<_>>(),
+            ["sha256:a", "sha256:b"]
+        );
+    }
+
+    #[test]
+    fn sizes_parse_with_decimal_and_binary_units() {
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert_eq!(parse_size("1 B"), Ok(1));
+        assert_eq!(parse_size("500MB"), Ok(500_000_000));
+        assert_eq!(parse_size("2tb"), Ok(2_000_000_000_000));
+        assert_eq!(parse_size("1GiB"), Ok(1 << 30));
+        assert_eq!(parse_size(" 1.5 kib "), Ok(1536));
+        assert_eq!(
+            parse_size("lots"),
+            Err("invalid size lots, expected e.g. 1024, 500MB, or 1GiB".into())
+        );
+        assert_eq!(
+            parse_size("5 PB"),
+            Err(
+                "unknown size unit pb, expected one of b, kb, mb, gb, tb, kib, mib, gib, or tib"
+                    .into()
+            )
+        );
+    }
+
+    #[test]
+    fn periods_start_on_the_day_at_or_before_the_date() {
+        let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        assert_eq!(period_start(date(2024, 3, 15), 1), date(2024, 3, 1));
+        assert_eq!(period_start(date(2024, 3, 20), 20), date(2024, 3, 20));
+        assert_eq!(period_start(date(2024, 3, 15), 20), date(2024, 2, 20));
+        assert_eq!(period_start(date(2024, 1, 5), 10), date(2023, 12, 10));
+        assert_eq!(period_start(date(2024, 3, 1), 28), date(2024, 2, 28));
+    }
+
+    #[test]
+    fn globs_match_any_run_of_characters() {
+        assert!(glob_match("app", "app"));
+        assert!(!glob_match("app", "apps"));
+        assert!(glob_match("team/*", "team/api"));
+        assert!(glob_match("team/*", "team/"));
+        assert!(glob_match("*-prod", "api-prod"));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(!glob_match("a*c", "ab"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("é*", "école"));
+    }
+
+    #[test]
+    fn prefixes_strip_the_shortest_match() {
+        assert_eq!(strip_prefix("team/", "team/api"), "api");
+        assert_eq!(strip_prefix("*/", "a/b/c"), "b/c");
+        assert_eq!(strip_prefix("other/", "team/api"), "team/api");
+        assert_eq!(strip_prefix("team/", "team/"), "team/");
+    }
+
+    /// a throttle of its own, so tests don't share the process wide one
+    fn throttle(
+        rate: f64,
+        retry_budget: Option<usize>,
+    ) -> Throttle {
+        Throttle {
+            bucket: Mutex::new(Bucket {
+                rate,
+                ceiling: rate,
+                adaptive: true,
+                tokens: rate,
+                refilled: None,
+                retry_budget,
+                retries: Vec::new(),
+                exhausted: false,
+            }),
+        }
+    }
+
+    #[test]
+    fn retries_are_spent_from_the_budget() {
+        let throttle = throttle(0.0, Some(2));
+        assert!(throttle.retry("DescribeImages"));
+        assert!(throttle.retry("DescribeImages"));
+        assert!(!throttle.retry("DescribeImages"));
+        let bucket = throttle.bucket.lock().unwrap();
+        assert_eq!(bucket.retries, [("DescribeImages", 2)]);
+        assert!(bucket.exhausted);
+    }
+
+    #[test]
+    fn adaptive_rates_halve_then_ramp_back_up() {
+        let throttle = throttle(4.0, None);
+        let rate = || throttle.bucket.lock().unwrap().rate;
+        assert!(throttle.slow_down());
+        assert!(close(rate(), 2.0));
+        for _ in 0..5 {
+            throttle.slow_down();
+        }
+        assert!(close(rate(), MIN_ADAPTIVE_RATE));
+        throttle.speed_up();
+        assert!(close(rate(), MIN_ADAPTIVE_RATE + 0.04));
+        for _ in 0..100 {
+            throttle.speed_up();
+        }
+        assert!(close(rate(), 4.0));
+        throttle.bucket.lock().unwrap().adaptive = false;
+        assert!(!throttle.slow_down());
+    }
+
+    #[test]
+    fn throttling_is_recognized_by_status_or_exception() {
+        let unknown = |status, body| {
+            RusotoError::<DescribeImagesError>::Unknown(
+                rusoto_core::request::BufferedHttpResponse {
+                    status: hyper::StatusCode::from_u16(status).unwrap(),
+                    body: hyper::Chunk::from(body).into_bytes(),
+                    headers: hyper::HeaderMap::default(),
+                },
+            )
+        };
+        assert!(throttled(&unknown(429, "")));
+        assert!(throttled(&unknown(
+            400,
+            "{\"__type\":\"ThrottlingException\"}"
+        )));
+        assert!(!throttled(&unknown(
+            400,
+            "{\"__type\":\"ServerException\"}"
+        )));
+        assert!(!throttled(&RusotoError::<DescribeImagesError>::Validation(
+            "ThrottlingException".into()
+        )));
+        assert_eq!(operation::<DescribeImagesError>(), "DescribeImages");
+    }
+
+    #[test]
+    fn influx_tags_escape_separators() {
+        assert_eq!(influx_tag("team/api"), "team/api");
+        assert_eq!(influx_tag("a,b=c d"), "a\\,b\\=c\\ d");
+        assert_eq!(influx_tag("say \"hi\""), "say\\ \"hi\"");
+    }
+
+    #[test]
+    fn the_schema_describes_the_serialized_report() {
+        let schema = serde_json::from_str::<Value>(REPORT_SCHEMA).unwrap();
+        let keys = |value: &Value| {
+            value
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect::<HashSet<_>>()
+        };
+        let described = |path: &[&str]| {
+            keys(
+                &path
+                    .iter()
+                    .fold(&schema["properties"], |schema, key| &schema[*key])["properties"],
+            )
+        };
+        let ecr = StubEcr::new(vec![vec![image("sha256:a", &["v1"], 1, 30)]]);
+        let mut repo = repos(&ecr, &Region::UsEast1, &scan()).unwrap().remove(0);
+        repo.account = Some("profile".into());
+
+        let record = keys(&serde_json::to_value(Record::from(&repo)).unwrap());
+        let repositories = described(&["repositories", "items"]);
+        assert!(
+            record.is_subset(&repositories),
+            "undescribed record keys {:?}",
+            record.difference(&repositories)
+        );
+        // the rest are only present when selected with --fields
+        for key in repositories.difference(&record) {
+            assert!(
+                Field::ALL.iter().any(|field| {
+                    key == field.key() || *key == format!("{}_millicents", field.key())
+                }),
+                "{} is neither a record key nor a field",
+                key
+            );
+        }
+
+        let opts = Opts::from_iter(["ecr-insights"]);
+        let metadata = Metadata::new(&Region::UsEast1, &opts, Some("123456789012"));
+        assert_eq!(
+            keys(&serde_json::to_value(metadata).unwrap()),
+            described(&["metadata"])
+        );
+        let totals = Totals::default().add(&repo);
+        assert_eq!(
+            keys(&serde_json::to_value(totals).unwrap()),
+            described(&["totals"])
+        );
+    }
 }
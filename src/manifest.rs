@@ -0,0 +1,244 @@
+use crate::{THROTTLE, WARNINGS};
+use rusoto_core::RusotoError;
+use rusoto_ecr::{BatchGetImageError, BatchGetImageRequest, Ecr, ImageIdentifier};
+use serde::Deserialize;
+use std::{collections::HashMap, error::Error};
+
+/// media types we know how to inspect, docker and oci manifests along with
+/// the lists/indexes used for multi-arch images
+const ACCEPTED_MEDIA_TYPES: &[&str] = &[
+    "application/vnd.docker.distribution.manifest.v2+json",
+    "application/vnd.docker.distribution.manifest.list.v2+json",
+    "application/vnd.oci.image.manifest.v1+json",
+    "application/vnd.oci.image.index.v1+json",
+];
+
+/// batch_get_image accepts at most 100 image ids per call
+const BATCH_SIZE: usize = 100;
+
+/// The subset of a docker v2 / oci image manifest (or manifest list) we use
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub media_type: Option<String>,
+    pub config: Option<Descriptor>,
+    #[serde(default)]
+    pub layers: Vec<Descriptor>,
+    /// child manifests of a manifest list / image index
+    #[serde(default)]
+    pub manifests: Vec<Descriptor>,
+    #[serde(default)]
+    pub annotations: HashMap<String, String>,
+    /// the manifest an oci artifact (signature, sbom, ...) is attached to
+    pub subject: Option<Descriptor>,
+    pub artifact_type: Option<String>,
+}
+
+/// config media types of runnable images, any other marking an artifact
+const IMAGE_CONFIG_MEDIA_TYPES: &[&str] = &[
+    "application/vnd.docker.container.image.v1+json",
+    "application/vnd.oci.image.config.v1+json",
+];
+
+impl Manifest {
+    /// an oci artifact (helm chart, signature, sbom, ...) rather than an image
+    pub fn is_artifact(&self) -> bool {
+        self.subject.is_some()
+            || self.artifact_type.is_some()
+            || self
+                .config
+                .as_ref()
+                .and_then(|config| config.media_type.as_deref())
+                .is_some_and(|media_type| !IMAGE_CONFIG_MEDIA_TYPES.contains(&media_type))
+    }
+
+    /// whether the manifest, its artifact type, or its config is of any of the
+    /// given media types
+    pub fn has_media_type(
+        &self,
+        media_types: &[String],
+    ) -> bool {
+        self.media_type
+            .iter()
+            .chain(&self.artifact_type)
+            .chain(self.config.iter().flat_map(|config| &config.media_type))
+            .any(|media_type| media_types.contains(media_type))
+    }
+
+    /// combined size of the config and layer blobs
+    pub fn blob_size(&self) -> i64 {
+        self.layers
+            .iter()
+            .chain(&self.config)
+            .map(|blob| blob.size)
+            .sum()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Descriptor {
+    pub media_type: Option<String>,
+    pub digest: String,
+    pub size: i64,
+    /// platform of a manifest list's child manifest
+    pub platform: Option<Platform>,
+}
+
+#[derive(Deserialize)]
+pub struct Platform {
+    pub architecture: String,
+    pub variant: Option<String>,
+}
+
+impl Platform {
+    /// architecture qualified by any variant, e.g. `arm64` or `arm/v7`
+    pub fn name(&self) -> String {
+        match &self.variant {
+            Some(variant) => format!("{}/{}", self.architecture, variant),
+            None => self.architecture.clone(),
+        }
+    }
+}
+
+/// fetches and parses the manifests of the given image digests, keyed by digest
+///
+/// manifests which can't be fetched or parsed are left out of the result, and
+/// images or repositories deleted mid-run are reported as warnings rather than
+/// failing the scan
+pub fn manifests(
+    ecr: &impl Ecr,
+    registry_id: Option<String>,
+    repository_name: &str,
+    digests: &[String],
+) -> Result<HashMap<String, Manifest>, Box<dyn Error>> {
+    digests
+        .chunks(BATCH_SIZE)
+        .try_fold(HashMap::new(), |mut manifests, batch| {
+            let request = BatchGetImageRequest {
+                registry_id: registry_id.clone(),
+                repository_name: repository_name.to_string(),
+                image_ids: batch
+                    .iter()
+                    .map(|digest| ImageIdentifier {
+                        image_digest: Some(digest.clone()),
+                        ..ImageIdentifier::default()
+                    })
+                    .collect(),
+                accepted_media_types: Some(
+                    ACCEPTED_MEDIA_TYPES
+                        .iter()
+                        .map(|media_type| media_type.to_string())
+                        .collect(),
+                ),
+            };
+            let result = THROTTLE
+                .call(|| ecr.batch_get_image(request.clone()))
+                .map_err(|err| *err);
+            let result = match result {
+                Err(RusotoError::Service(BatchGetImageError::RepositoryNotFound(_))) => {
+                    WARNINGS.add(
+                        "repositories deleted during the scan",
+                        repository_name.to_string(),
+                    );
+                    return Ok(manifests);
+                }
+                result => result?,
+            };
+            for failure in result.failures.unwrap_or_default() {
+                WARNINGS.add(
+                    "manifests that couldn't be fetched",
+                    format!(
+                        "{}@{}: {}",
+                        repository_name,
+                        failure
+                            .image_id
+                            .and_then(|image_id| image_id.image_digest)
+                            .unwrap_or_default(),
+                        failure
+                            .failure_reason
+                            .or(failure.failure_code)
+                            .unwrap_or_default()
+                    ),
+                );
+            }
+            manifests.extend(
+                result
+                    .images
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|image| {
+                        let digest = image.image_id?.image_digest?;
+                        let manifest = serde_json::from_str(&image.image_manifest?).ok()?;
+                        Some((digest, manifest))
+                    }),
+            );
+            Ok(manifests)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(json: &str) -> Manifest {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn images_are_told_from_artifacts_by_their_config() {
+        let image = manifest(
+            r#"{"config": {"mediaType": "application/vnd.oci.image.config.v1+json",
+                "digest": "sha256:c", "size": 10}}"#,
+        );
+        assert!(!image.is_artifact());
+        let chart = manifest(
+            r#"{"config": {"mediaType": "application/vnd.cncf.helm.config.v1+json",
+                "digest": "sha256:c", "size": 10}}"#,
+        );
+        assert!(chart.is_artifact());
+        let signature = manifest(r#"{"subject": {"digest": "sha256:s", "size": 1}}"#);
+        assert!(signature.is_artifact());
+        assert!(manifest(r#"{"artifactType": "application/spdx+json"}"#).is_artifact());
+        assert!(!manifest("{}").is_artifact());
+    }
+
+    #[test]
+    fn media_types_match_the_manifest_artifact_or_config() {
+        let types = |types: &[&str]| types.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        let signature = manifest(
+            r#"{"mediaType": "application/vnd.oci.image.manifest.v1+json",
+                "artifactType": "application/vnd.dev.cosign.artifact.sig.v1+json",
+                "config": {"mediaType": "application/vnd.oci.empty.v1+json",
+                "digest": "sha256:c", "size": 2}}"#,
+        );
+        assert!(signature.has_media_type(&types(&["application/vnd.oci.image.manifest.v1+json"])));
+        assert!(
+            signature.has_media_type(&types(&["application/vnd.dev.cosign.artifact.sig.v1+json"]))
+        );
+        assert!(signature.has_media_type(&types(&["application/vnd.oci.empty.v1+json"])));
+        assert!(!signature.has_media_type(&types(&["application/spdx+json"])));
+        assert!(!signature.has_media_type(&[]));
+    }
+
+    #[test]
+    fn blob_size_counts_the_config_and_layers() {
+        let image = manifest(
+            r#"{"config": {"digest": "sha256:c", "size": 10},
+                "layers": [{"digest": "sha256:a", "size": 100},
+                           {"digest": "sha256:b", "size": 1000}]}"#,
+        );
+        assert_eq!(image.blob_size(), 1110);
+        assert_eq!(manifest("{}").blob_size(), 0);
+    }
+
+    #[test]
+    fn platforms_are_qualified_by_their_variant() {
+        let platform = |json| serde_json::from_str::<Platform>(json).unwrap().name();
+        assert_eq!(platform(r#"{"architecture": "amd64"}"#), "amd64");
+        assert_eq!(
+            platform(r#"{"architecture": "arm", "variant": "v7"}"#),
+            "arm/v7"
+        );
+    }
+}
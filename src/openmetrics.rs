@@ -0,0 +1,169 @@
+use crate::Repo;
+use chrono::Utc;
+use std::io::{Error, ErrorKind, Result, Write};
+
+/// a gauge reported per repository
+struct Family {
+    name: &'static str,
+    help: &'static str,
+    /// must end the metric name, per the spec
+    unit: Option<&'static str>,
+    value: fn(&Repo) -> f64,
+}
+
+const FAMILIES: &[Family] = &[
+    Family {
+        name: "ecr_repository_monthly_cost_dollars",
+        help: "Estimated monthly storage cost of all hosted images.",
+        unit: Some("dollars"),
+        value: |repo| repo.monthly_cost(),
+    },
+    Family {
+        name: "ecr_repository_monthly_capped_cost_dollars",
+        help: "Estimated monthly storage cost of the images a capped retention policy keeps.",
+        unit: Some("dollars"),
+        value: |repo| repo.monthly_capped_cost(),
+    },
+    Family {
+        name: "ecr_repository_latest_image_size_bytes",
+        help: "Size of the most recently pushed image.",
+        unit: Some("bytes"),
+        value: |repo| repo.latest_image_size as f64,
+    },
+    Family {
+        name: "ecr_repository_aggregate_image_size_bytes",
+        help: "Combined size of all hosted images.",
+        unit: Some("bytes"),
+        value: |repo| repo.aggregate_image_size as f64,
+    },
+    Family {
+        name: "ecr_repository_hosted_images",
+        help: "Number of hosted images.",
+        unit: None,
+        value: |repo| repo.hosted_images as f64,
+    },
+];
+
+/// `[a-zA-Z_:][a-zA-Z0-9_:]*` for metric names, without colons for labels
+fn valid_name(
+    name: &str,
+    colons: bool,
+) -> bool {
+    let allowed = |c: char| c.is_ascii_alphabetic() || c == '_' || (colons && c == ':');
+    let mut chars = name.chars();
+    chars.next().is_some_and(allowed) && chars.all(|c| allowed(c) || c.is_ascii_digit())
+}
+
+fn validate(
+    name: &str,
+    colons: bool,
+) -> Result<()> {
+    if valid_name(name, colons) {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("{} isn't a valid openmetrics name", name),
+        ))
+    }
+}
+
+/// escapes backslashes, double quotes, and line feeds in label values
+fn label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// writes per repository gauges in the openmetrics text format, timestamped
+/// with the time of the run and terminated by `# EOF`
+pub fn write(
+    writer: &mut impl Write,
+    repos: &[Repo],
+) -> Result<()> {
+    let now = Utc::now();
+    let timestamp = format!("{}.{:03}", now.timestamp(), now.timestamp_subsec_millis());
+    for family in FAMILIES {
+        validate(family.name, true)?;
+        writeln!(writer, "# TYPE {} gauge", family.name)?;
+        if let Some(unit) = family.unit {
+            writeln!(writer, "# UNIT {} {}", family.name, unit)?;
+        }
+        writeln!(writer, "# HELP {} {}", family.name, family.help)?;
+        for repo in repos {
+            let mut labels = vec![("repository", repo.name.as_str())];
+            if let Some(account) = &repo.account {
+                labels.insert(0, ("account", account));
+            }
+            for (label, _) in &labels {
+                validate(label, false)?;
+            }
+            writeln!(
+                writer,
+                "{}{{{}}} {} {}",
+                family.name,
+                labels
+                    .iter()
+                    .map(|(label, value)| format!("{}=\"{}\"", label, label_value(value)))
+                    .collect::<Vec<_>>()
+                    .join(","),
+                (family.value)(repo),
+                timestamp
+            )?;
+        }
+    }
+    writeln!(writer, "# EOF")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_values_escape_backslashes_quotes_and_newlines() {
+        assert_eq!(label_value("team/api"), "team/api");
+        assert_eq!(label_value("a\\b"), "a\\\\b");
+        assert_eq!(label_value("say \"hi\""), "say \\\"hi\\\"");
+        assert_eq!(label_value("two\nlines"), "two\\nlines");
+        assert_eq!(label_value("\\\""), "\\\\\\\"");
+    }
+
+    #[test]
+    fn names_follow_the_spec() {
+        assert!(valid_name("ecr_repository_hosted_images", true));
+        assert!(valid_name("_private:ratio", true));
+        assert!(!valid_name("_private:ratio", false));
+        assert!(!valid_name("9lives", true));
+        assert!(!valid_name("has-dash", true));
+        assert!(!valid_name("", true));
+        assert!(validate("9lives", false).is_err());
+    }
+
+    #[test]
+    fn family_names_are_valid() {
+        for family in FAMILIES {
+            assert!(valid_name(family.name, true), "{}", family.name);
+            if let Some(unit) = family.unit {
+                assert!(family.name.ends_with(unit), "{}", family.name);
+            }
+        }
+    }
+
+    #[test]
+    fn writes_labelled_samples_ending_in_eof() {
+        let mut repo = crate::tests::repo("team/\"api\"");
+        repo.account = Some("prod".into());
+        let mut out = Vec::new();
+        write(&mut out, &[repo]).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with(
+            "# TYPE ecr_repository_monthly_cost_dollars gauge\n\
+             # UNIT ecr_repository_monthly_cost_dollars dollars\n"
+        ));
+        assert!(out.contains(
+            "\necr_repository_hosted_images{account=\"prod\",repository=\"team/\\\"api\\\"\"} 2 "
+        ));
+        assert!(out.ends_with("# EOF\n"));
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{Metadata, Repo};
+use chrono::Utc;
+use parquet::{
+    data_type::{ByteArray, ByteArrayType, DataType, DoubleType, Int64Type},
+    file::{
+        properties::WriterProperties,
+        writer::{SerializedFileWriter, SerializedRowGroupWriter},
+    },
+    schema::parser::parse_message_type,
+};
+use std::{error::Error, fs::File, path::Path, sync::Arc};
+
+/// columns in write order, the per repository record along with the run's
+/// region, account, and time to partition by
+const SCHEMA: &str = "
+message repository {
+    REQUIRED BYTE_ARRAY region (UTF8);
+    OPTIONAL BYTE_ARRAY account (UTF8);
+    REQUIRED INT64 generated_at (TIMESTAMP(MILLIS, true));
+    REQUIRED BYTE_ARRAY name (UTF8);
+    REQUIRED BYTE_ARRAY arn (UTF8);
+    OPTIONAL BYTE_ARRAY last_pushed_at (UTF8);
+    REQUIRED INT64 latest_image_size;
+    REQUIRED INT64 aggregate_image_size;
+    REQUIRED INT64 recent_image_size;
+    REQUIRED INT64 hosted_images;
+    REQUIRED DOUBLE monthly_cost;
+    REQUIRED DOUBLE monthly_capped_cost;
+}
+";
+
+/// values and definition levels of an optional string column
+fn optional(values: Vec<Option<&str>>) -> (Vec<ByteArray>, Vec<i16>) {
+    let levels = values.iter().map(|value| value.is_some() as i16).collect();
+    (
+        values.into_iter().flatten().map(ByteArray::from).collect(),
+        levels,
+    )
+}
+
+/// writes the next column of a row group
+fn column<T: DataType>(
+    row_group: &mut SerializedRowGroupWriter<'_, File>,
+    values: &[T::T],
+    levels: Option<&[i16]>,
+) -> Result<(), Box<dyn Error>> {
+    let mut column = row_group
+        .next_column()?
+        .ok_or("parquet schema has too few columns")?;
+    column.typed::<T>().write_batch(values, levels, None)?;
+    column.close()?;
+    Ok(())
+}
+
+/// writes repositories as a single row group parquet file
+pub fn write(
+    repos: &[Repo],
+    metadata: &Metadata,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let mut writer = SerializedFileWriter::new(
+        File::create(path)?,
+        Arc::new(parse_message_type(SCHEMA)?),
+        Arc::new(WriterProperties::builder().build()),
+    )?;
+    let generated_at = Utc::now().timestamp_millis();
+    let mut row_group = writer.next_row_group()?;
+    let strings = |value: fn(&Repo) -> &str| {
+        repos
+            .iter()
+            .map(|repo| ByteArray::from(value(repo)))
+            .collect::<Vec<_>>()
+    };
+    let integers = |value: fn(&Repo) -> i64| repos.iter().map(value).collect::<Vec<_>>();
+    let doubles = |value: fn(&Repo) -> f64| repos.iter().map(value).collect::<Vec<_>>();
+
+    column::<ByteArrayType>(
+        &mut row_group,
+        &vec![ByteArray::from(metadata.region.as_str()); repos.len()],
+        None,
+    )?;
+    let (accounts, levels) = optional(
+        repos
+            .iter()
+            .map(|repo| repo.account.as_deref().or(metadata.account.as_deref()))
+            .collect(),
+    );
+    column::<ByteArrayType>(&mut row_group, &accounts, Some(&levels))?;
+    column::<Int64Type>(&mut row_group, &vec![generated_at; repos.len()], None)?;
+    column::<ByteArrayType>(&mut row_group, &strings(|repo| &repo.name), None)?;
+    column::<ByteArrayType>(&mut row_group, &strings(|repo| &repo.arn), None)?;
+    let (last_pushed_at, levels) = optional(
+        repos
+            .iter()
+            .map(|repo| repo.last_pushed_at.as_deref())
+            .collect(),
+    );
+    column::<ByteArrayType>(&mut row_group, &last_pushed_at, Some(&levels))?;
+    column::<Int64Type>(
+        &mut row_group,
+        &integers(|repo| repo.latest_image_size),
+        None,
+    )?;
+    column::<Int64Type>(
+        &mut row_group,
+        &integers(|repo| repo.aggregate_image_size),
+        None,
+    )?;
+    column::<Int64Type>(
+        &mut row_group,
+        &integers(|repo| repo.recent_image_size),
+        None,
+    )?;
+    column::<Int64Type>(
+        &mut row_group,
+        &integers(|repo| repo.hosted_images as i64),
+        None,
+    )?;
+    column::<DoubleType>(&mut row_group, &doubles(|repo| repo.monthly_cost()), None)?;
+    column::<DoubleType>(
+        &mut row_group,
+        &doubles(|repo| repo.monthly_capped_cost()),
+        None,
+    )?;
+    row_group.close()?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Opts;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use rusoto_core::Region;
+    use structopt::StructOpt;
+
+    #[test]
+    fn optional_values_are_packed_with_their_levels() {
+        let (values, levels) = optional(vec![Some("a"), None, Some("b")]);
+        assert_eq!(values, [ByteArray::from("a"), ByteArray::from("b")]);
+        assert_eq!(levels, [1, 0, 1]);
+    }
+
+    #[test]
+    fn writes_a_row_per_repository() {
+        let path =
+            std::env::temp_dir().join(format!("ecr-insights-{}.parquet", std::process::id()));
+        let metadata = Metadata::new(
+            &Region::UsEast1,
+            &Opts::from_iter(["ecr-insights"]),
+            Some("123456789012"),
+        );
+        let repos = [crate::tests::repo("a"), crate::tests::repo("b")];
+        write(&repos, &metadata, &path).unwrap();
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let metadata = reader.metadata().file_metadata();
+        assert_eq!(metadata.num_rows(), 2);
+        assert_eq!(
+            metadata
+                .schema_descr()
+                .columns()
+                .iter()
+                .map(|column| column.name())
+                .collect::<Vec<_>>(),
+            parse_message_type(SCHEMA)
+                .unwrap()
+                .get_fields()
+                .iter()
+                .map(|field| field.name())
+                .collect::<Vec<_>>()
+        );
+    }
+}
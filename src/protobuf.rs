@@ -0,0 +1,253 @@
+use crate::{Metadata, Record, Totals};
+use std::{fs, io::Result, path::Path};
+
+/// the committed schema reports are encoded against
+pub const SCHEMA: &str = include_str!("../proto/report.proto");
+
+/// `Report.version`, bumped when the meaning of an existing field changes
+const VERSION: u32 = 1;
+
+const VARINT: u32 = 0;
+const FIXED64: u32 = 1;
+const LENGTH_DELIMITED: u32 = 2;
+
+/// an encoded message, fields written in number order and proto3 defaults
+/// (zero, empty) left out as the protobuf runtimes do
+#[derive(Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn varint(
+        &mut self,
+        mut value: u64,
+    ) {
+        while value >= 0x80 {
+            self.0.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    fn key(
+        &mut self,
+        field: u32,
+        wire_type: u32,
+    ) {
+        self.varint(u64::from(field << 3 | wire_type));
+    }
+
+    fn uint(
+        mut self,
+        field: u32,
+        value: u64,
+    ) -> Self {
+        if value != 0 {
+            self.key(field, VARINT);
+            self.varint(value);
+        }
+        self
+    }
+
+    /// `int64`, negative values as their ten byte two's complement
+    fn int(
+        self,
+        field: u32,
+        value: i64,
+    ) -> Self {
+        self.uint(field, value as u64)
+    }
+
+    fn double(
+        mut self,
+        field: u32,
+        value: f64,
+    ) -> Self {
+        if value != 0.0 {
+            self.key(field, FIXED64);
+            self.0.extend_from_slice(&value.to_le_bytes());
+        }
+        self
+    }
+
+    fn bytes(
+        mut self,
+        field: u32,
+        value: &[u8],
+    ) -> Self {
+        self.key(field, LENGTH_DELIMITED);
+        self.varint(value.len() as u64);
+        self.0.extend_from_slice(value);
+        self
+    }
+
+    fn string(
+        self,
+        field: u32,
+        value: &str,
+    ) -> Self {
+        if value.is_empty() {
+            self
+        } else {
+            self.bytes(field, value.as_bytes())
+        }
+    }
+
+    /// an `optional string`, written whenever present even if empty
+    fn optional_string(
+        self,
+        field: u32,
+        value: Option<&str>,
+    ) -> Self {
+        match value {
+            Some(value) => self.bytes(field, value.as_bytes()),
+            None => self,
+        }
+    }
+
+    fn message(
+        self,
+        field: u32,
+        value: Message,
+    ) -> Self {
+        self.bytes(field, &value.0)
+    }
+}
+
+fn metadata_message(metadata: &Metadata) -> Message {
+    Message::default()
+        .string(1, &metadata.region)
+        .optional_string(2, metadata.account.as_deref())
+        .string(3, metadata.version)
+        .string(4, &metadata.generated_at)
+}
+
+fn repository_message(record: &Record) -> Message {
+    Message::default()
+        .optional_string(1, record.account)
+        .string(2, record.name)
+        .string(3, record.arn)
+        .optional_string(4, record.last_pushed_at)
+        .int(5, record.latest_image_size)
+        .int(6, record.aggregate_image_size)
+        .int(7, record.recent_image_size)
+        .uint(8, record.hosted_images as u64)
+        .double(9, record.monthly_cost)
+        .double(10, record.monthly_capped_cost)
+}
+
+fn totals_message(totals: &Totals) -> Message {
+    Message::default()
+        .uint(1, totals.repositories as u64)
+        .uint(2, totals.hosted_images as u64)
+        .double(3, totals.monthly_cost)
+        .double(4, totals.monthly_capped_cost)
+        .int(5, totals.aggregate_image_size)
+        .int(6, totals.recent_image_size)
+}
+
+/// writes an encoded `Report` message
+pub fn write(
+    records: &[Record],
+    metadata: &Metadata,
+    totals: &Totals,
+    path: &Path,
+) -> Result<()> {
+    let report = records.iter().fold(
+        Message::default()
+            .uint(1, u64::from(VERSION))
+            .message(2, metadata_message(metadata)),
+        |report, record| report.message(3, repository_message(record)),
+    );
+    fs::write(path, report.message(4, totals_message(totals)).0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varint(value: u64) -> Vec<u8> {
+        let mut message = Message::default();
+        message.varint(value);
+        message.0
+    }
+
+    #[test]
+    fn varints_take_seven_bits_a_byte() {
+        assert_eq!(varint(0), [0x00]);
+        assert_eq!(varint(1), [0x01]);
+        assert_eq!(varint(127), [0x7f]);
+        assert_eq!(varint(128), [0x80, 0x01]);
+        assert_eq!(varint(300), [0xac, 0x02]);
+        assert_eq!(varint(16_384), [0x80, 0x80, 0x01]);
+    }
+
+    #[test]
+    fn varints_span_ten_bytes_at_most() {
+        assert_eq!(
+            varint(u64::MAX),
+            [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]
+        );
+    }
+
+    #[test]
+    fn fields_are_keyed_by_number_and_wire_type() {
+        assert_eq!(Message::default().uint(1, 150).0, [0x08, 0x96, 0x01]);
+        assert_eq!(Message::default().uint(16, 1).0, [0x80, 0x01, 0x01]);
+        assert_eq!(
+            Message::default().double(2, 1.0).0,
+            [0x11, 0, 0, 0, 0, 0, 0, 0xf0, 0x3f]
+        );
+    }
+
+    #[test]
+    fn negative_ints_are_ten_bytes() {
+        assert_eq!(
+            Message::default().int(1, -1).0,
+            [0x08, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]
+        );
+    }
+
+    #[test]
+    fn defaults_are_left_out() {
+        let message = Message::default()
+            .uint(1, 0)
+            .int(2, 0)
+            .double(3, 0.0)
+            .string(4, "")
+            .optional_string(5, None);
+        assert!(message.0.is_empty());
+    }
+
+    #[test]
+    fn strings_are_length_delimited() {
+        assert_eq!(
+            Message::default().string(2, "testing").0,
+            [0x12, 0x07, b't', b'e', b's', b't', b'i', b'n', b'g']
+        );
+        assert_eq!(
+            Message::default().optional_string(3, Some("")).0,
+            [0x1a, 0x00]
+        );
+    }
+
+    #[test]
+    fn long_values_have_multi_byte_lengths() {
+        let value = "x".repeat(200);
+        let encoded = Message::default().string(1, &value).0;
+        assert_eq!(encoded[..3], [0x0a, 0xc8, 0x01]);
+        assert_eq!(encoded.len(), 3 + 200);
+    }
+
+    #[test]
+    fn messages_nest_as_length_delimited_fields() {
+        let inner = Message::default().uint(1, 150);
+        assert_eq!(
+            Message::default().message(3, inner).0,
+            [0x1a, 0x03, 0x08, 0x96, 0x01]
+        );
+        assert_eq!(
+            Message::default().message(3, Message::default()).0,
+            [0x1a, 0x00]
+        );
+    }
+}
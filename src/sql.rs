@@ -0,0 +1,186 @@
+use crate::{
+    fields::{Cell, Field, Style, Units},
+    Repo,
+};
+use std::io::{Result, Write};
+
+/// column type of a field, portable between postgres and sqlite
+fn column_type(
+    field: Field,
+    style: &Style,
+) -> &'static str {
+    match field {
+        field if field.is_cost() && style.units == Units::Millicents => "BIGINT",
+        Field::Size
+        | Field::AggregateSize
+        | Field::ArtifactSize
+        | Field::CappedSize
+        | Field::LargestImageSize
+        | Field::MinSize
+        | Field::MedianSize
+        | Field::MaxSize
+        | Field::ReconciledSize
+        | Field::SizeDiscrepancy
+        | Field::Images
+        | Field::AgeDays => "BIGINT",
+        Field::Cost
+        | Field::CappedCost
+        | Field::CostPerImage
+        | Field::LatestCost
+        | Field::HistoryCost
+        | Field::Layers
+        | Field::SizeStddev
+        | Field::PercentOfTotal => "DOUBLE PRECISION",
+        _ => "TEXT",
+    }
+}
+
+/// double quoted identifier, so table names with odd characters stay one token
+fn identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn literal(cell: &Cell) -> String {
+    match cell {
+        Cell::Text(None) => "NULL".into(),
+        Cell::Text(Some(text)) => format!("'{}'", text.replace('\'', "''")),
+        Cell::Cost(cost) if !cost.is_finite() => "NULL".into(),
+        Cell::Cost(cost) => cost.to_string(),
+        Cell::Number(Some(number)) | Cell::Percent(Some(number)) if number.is_finite() => {
+            number.to_string()
+        }
+        Cell::Number(_) | Cell::Percent(_) | Cell::Count(None) => "NULL".into(),
+        integer => integer.to_json().to_string(),
+    }
+}
+
+/// writes a `CREATE TABLE IF NOT EXISTS` statement for the given fields
+/// followed by one `INSERT` per repository
+pub fn write(
+    writer: &mut impl Write,
+    table: &str,
+    fields: &[Field],
+    style: &Style,
+    repos: &[Repo],
+) -> Result<()> {
+    let table = identifier(table);
+    writeln!(
+        writer,
+        "CREATE TABLE IF NOT EXISTS {} ({});",
+        table,
+        fields
+            .iter()
+            .map(|field| {
+                format!(
+                    "{} {}",
+                    identifier(&field.column(style)),
+                    column_type(*field, style)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    )?;
+    let columns = fields
+        .iter()
+        .map(|field| identifier(&field.column(style)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    for repo in repos {
+        writeln!(
+            writer,
+            "INSERT INTO {} ({}) VALUES ({});",
+            table,
+            columns,
+            fields
+                .iter()
+                .map(|field| literal(&field.value(repo, style)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fields::Unit;
+
+    #[test]
+    fn identifiers_double_embedded_quotes() {
+        assert_eq!(identifier("repositories"), "\"repositories\"");
+        assert_eq!(identifier("my \"table\""), "\"my \"\"table\"\"\"");
+    }
+
+    #[test]
+    fn text_literals_double_embedded_quotes() {
+        assert_eq!(literal(&Cell::Text(Some("app".into()))), "'app'");
+        assert_eq!(literal(&Cell::Text(Some("o'brien".into()))), "'o''brien'");
+        assert_eq!(literal(&Cell::Text(Some("'); --".into()))), "'''); --'");
+        assert_eq!(literal(&Cell::Text(Some(String::new()))), "''");
+        assert_eq!(literal(&Cell::Text(None)), "NULL");
+    }
+
+    #[test]
+    fn unavailable_numbers_are_null() {
+        assert_eq!(literal(&Cell::Cost(f64::NAN)), "NULL");
+        assert_eq!(literal(&Cell::Number(None)), "NULL");
+        assert_eq!(literal(&Cell::Number(Some(f64::INFINITY))), "NULL");
+        assert_eq!(literal(&Cell::Percent(None)), "NULL");
+        assert_eq!(literal(&Cell::Count(None)), "NULL");
+    }
+
+    #[test]
+    fn numbers_are_bare() {
+        assert_eq!(literal(&Cell::Cost(1.5)), "1.5");
+        assert_eq!(literal(&Cell::Number(Some(2.25))), "2.25");
+        assert_eq!(literal(&Cell::Integer(-2)), "-2");
+        assert_eq!(literal(&Cell::Count(Some(3))), "3");
+        assert_eq!(literal(&Cell::Millicents(12)), "12");
+        assert_eq!(literal(&Cell::Size(2048, Some(Unit::Kib))), "2");
+    }
+
+    #[test]
+    fn costs_are_integers_in_millicents() {
+        let millicents = Style {
+            units: Units::Millicents,
+            ..Style::default()
+        };
+        assert_eq!(
+            column_type(Field::Cost, &Style::default()),
+            "DOUBLE PRECISION"
+        );
+        assert_eq!(column_type(Field::Cost, &millicents), "BIGINT");
+        assert_eq!(column_type(Field::Size, &millicents), "BIGINT");
+        assert_eq!(column_type(Field::Name, &millicents), "TEXT");
+    }
+
+    #[test]
+    fn writes_a_table_and_a_row_per_repository() {
+        let mut out = Vec::new();
+        write(
+            &mut out,
+            "ecr \"report\"",
+            &[Field::Name, Field::Images, Field::Cost],
+            &Style::default(),
+            &[crate::tests::repo("o'brien")],
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            "CREATE TABLE IF NOT EXISTS \"ecr \"\"report\"\"\" (\"name\" TEXT, \
+             \"hosted_images\" BIGINT, \"monthly_cost\" DOUBLE PRECISION);"
+        );
+        assert!(
+            lines[1].starts_with(
+                "INSERT INTO \"ecr \"\"report\"\"\" (\"name\", \"hosted_images\", \
+                 \"monthly_cost\") VALUES ('o''brien', 2, "
+            ),
+            "{}",
+            lines[1]
+        );
+        assert_eq!(lines.len(), 2);
+    }
+}
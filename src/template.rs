@@ -0,0 +1,137 @@
+use crate::{
+    fields::{Cell, Field, Style},
+    Repo,
+};
+use std::str::FromStr;
+
+enum Piece {
+    Literal(String),
+    Field(Field),
+}
+
+/// A user supplied line format for `--format template`, with `{field}`
+/// placeholders named as in `--fields` and `{{`/`}}` for literal braces
+pub struct Template(Vec<Piece>);
+
+impl Template {
+    /// renders a repository, with costs as bare numbers so templates can
+    /// choose their own currency formatting, e.g. `${cost}`
+    pub fn render(
+        &self,
+        repo: &Repo,
+        style: &Style,
+    ) -> String {
+        self.0
+            .iter()
+            .map(|piece| match piece {
+                Piece::Literal(text) => text.clone(),
+                Piece::Field(field) => match field.value(repo, style) {
+                    Cell::Cost(cost) => format!("{:.2}", cost),
+                    cell => cell.to_string(),
+                },
+            })
+            .collect()
+    }
+
+    /// whether a placeholder names the field
+    pub fn uses(
+        &self,
+        field: Field,
+    ) -> bool {
+        self.0
+            .iter()
+            .any(|piece| matches!(piece, Piece::Field(other) if *other == field))
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => {
+                                return Err(format!("unclosed placeholder {{{} in template", name))
+                            }
+                        }
+                    }
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(literal.split_off(0)));
+                    }
+                    pieces.push(Piece::Field(name.parse()?));
+                }
+                '}' => return Err("unmatched } in template, use }} for a literal brace".into()),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+        Ok(Template(pieces))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        template
+            .parse::<Template>()
+            .unwrap()
+            .render(&crate::tests::repo("app"), &Style::default())
+    }
+
+    #[test]
+    fn placeholders_are_replaced_by_field_values() {
+        assert_eq!(render("{name}: {images} images"), "app: 2 images");
+        assert_eq!(render("plain text"), "plain text");
+        assert_eq!(render(""), "");
+    }
+
+    #[test]
+    fn costs_render_as_bare_numbers() {
+        assert_eq!(render("{name} ${cost}"), "app $0.30");
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(render("{{{name}}}"), "{app}");
+        assert_eq!(render("}}{{"), "}{");
+    }
+
+    #[test]
+    fn malformed_templates_are_rejected() {
+        let error = |template: &str| template.parse::<Template>().err().unwrap();
+        assert_eq!(error("{name"), "unclosed placeholder {name in template");
+        assert_eq!(
+            error("name}"),
+            "unmatched } in template, use }} for a literal brace"
+        );
+        assert!(error("{nope}").starts_with("unknown field nope, expected one of account"));
+    }
+
+    #[test]
+    fn uses_reports_the_fields_named() {
+        let template = "{name} {{cost}}".parse::<Template>().unwrap();
+        assert!(template.uses(Field::Name));
+        assert!(!template.uses(Field::Cost));
+    }
+}
@@ -0,0 +1,168 @@
+use serde_json::{Map, Value};
+use std::io::{Result, Write};
+
+/// writes a json object as a toml document, nested objects becoming tables
+/// and arrays of objects arrays of tables. toml has no null, so null values
+/// are left out
+pub fn write(
+    writer: &mut impl Write,
+    value: &Value,
+) -> Result<()> {
+    match value {
+        Value::Object(fields) => table(writer, &[], fields),
+        _ => Ok(()),
+    }
+}
+
+/// a table's plain keys, which toml requires ahead of its sub tables, then
+/// its sub tables under their dotted paths
+fn table(
+    writer: &mut impl Write,
+    path: &[&str],
+    fields: &Map<String, Value>,
+) -> Result<()> {
+    for (name, value) in fields {
+        if !is_table(value) && !value.is_null() {
+            writeln!(writer, "{} = {}", key(name), inline(value))?;
+        }
+    }
+    for (name, value) in fields {
+        let path = [path, &[name.as_str()]].concat();
+        let header = path
+            .iter()
+            .map(|name| key(name))
+            .collect::<Vec<_>>()
+            .join(".");
+        match value {
+            Value::Object(fields) => {
+                writeln!(writer)?;
+                writeln!(writer, "[{}]", header)?;
+                table(writer, &path, fields)?;
+            }
+            Value::Array(values) if is_table(value) => {
+                for fields in values.iter().filter_map(Value::as_object) {
+                    writeln!(writer)?;
+                    writeln!(writer, "[[{}]]", header)?;
+                    table(writer, &path, fields)?;
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// objects, and non empty arrays of only objects, are written as tables
+fn is_table(value: &Value) -> bool {
+    match value {
+        Value::Object(_) => true,
+        Value::Array(values) => !values.is_empty() && values.iter().all(Value::is_object),
+        _ => false,
+    }
+}
+
+/// keys of letters, digits, `_`, and `-` are bare, others quoted
+fn key(name: &str) -> String {
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        name.to_string()
+    } else {
+        Value::String(name.to_string()).to_string()
+    }
+}
+
+/// json's string escapes are valid in toml basic strings, and serde_json
+/// writes floats with a fractional part, keeping them floats in toml
+fn inline(value: &Value) -> String {
+    match value {
+        Value::Array(values) => format!(
+            "[{}]",
+            values
+                .iter()
+                .filter(|value| !value.is_null())
+                .map(inline)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Object(fields) => format!(
+            "{{ {} }}",
+            fields
+                .iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(name, value)| format!("{} = {}", key(name), inline(value)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        scalar => scalar.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn toml(value: Value) -> String {
+        let mut out = Vec::new();
+        write(&mut out, &value).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn keys_outside_the_bare_set_are_quoted() {
+        assert_eq!(key("monthly_cost"), "monthly_cost");
+        assert_eq!(key("team-api2"), "team-api2");
+        assert_eq!(key("team/api"), "\"team/api\"");
+        assert_eq!(key("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(key(""), "\"\"");
+    }
+
+    #[test]
+    fn strings_keep_json_escapes_and_floats_their_fraction() {
+        assert_eq!(inline(&json!("a \"b\"\n")), "\"a \\\"b\\\"\\n\"");
+        assert_eq!(inline(&json!(1.0)), "1.0");
+        assert_eq!(inline(&json!([1, null, 2])), "[1, 2]");
+        assert_eq!(inline(&json!({"a": 1, "b": null})), "{ a = 1 }");
+    }
+
+    #[test]
+    fn plain_keys_precede_tables_and_nulls_are_left_out() {
+        assert_eq!(
+            toml(json!({
+                "metadata": {"region": "us-east-1", "account": null},
+                "repositories": [{"name": "a"}, {"name": "b"}],
+                "version": 1,
+            })),
+            "version = 1\n\
+             \n\
+             [metadata]\n\
+             region = \"us-east-1\"\n\
+             \n\
+             [[repositories]]\n\
+             name = \"a\"\n\
+             \n\
+             [[repositories]]\n\
+             name = \"b\"\n"
+        );
+    }
+
+    #[test]
+    fn empty_and_mixed_arrays_are_inline() {
+        assert!(!is_table(&json!([])));
+        assert!(!is_table(&json!([{"a": 1}, 2])));
+        assert!(is_table(&json!([{"a": 1}])));
+        assert_eq!(
+            toml(json!({"empty": [], "mixed": [{"a": 1}, 2]})),
+            "empty = []\nmixed = [{ a = 1 }, 2]\n"
+        );
+    }
+
+    #[test]
+    fn only_objects_are_written() {
+        assert_eq!(toml(json!([1, 2])), "");
+        assert_eq!(toml(json!("text")), "");
+    }
+}
@@ -0,0 +1,209 @@
+use crate::{format, Repo};
+use std::{
+    collections::BTreeMap,
+    io::{Result, Write},
+};
+use tabwriter::TabWriter;
+
+/// a namespace level of repository names, costs rolled up from below
+#[derive(Default)]
+struct Node {
+    monthly_cost: f64,
+    monthly_capped_cost: f64,
+    children: BTreeMap<String, Node>,
+}
+
+impl Node {
+    fn add(
+        &mut self,
+        repo: &Repo,
+    ) {
+        self.monthly_cost += repo.monthly_cost();
+        self.monthly_capped_cost += repo.monthly_capped_cost();
+    }
+
+    fn write(
+        &self,
+        writer: &mut impl Write,
+        indent: &str,
+    ) -> Result<()> {
+        let last = self.children.len().saturating_sub(1);
+        for (index, (name, child)) in self.children.iter().enumerate() {
+            let (branch, continuation) = if index == last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            writeln!(
+                writer,
+                "{}{}{}\t{}\t=> {}",
+                indent,
+                branch,
+                name,
+                format::cost(child.monthly_cost),
+                format::cost(child.monthly_capped_cost)
+            )?;
+            child.write(writer, &format!("{}{}", indent, continuation))?;
+        }
+        Ok(())
+    }
+
+    /// graphviz statements for the nodes beneath this one, identified by their
+    /// paths and sized by their share of the `total` cost
+    fn write_dot(
+        &self,
+        writer: &mut impl Write,
+        path: &str,
+        total: f64,
+    ) -> Result<()> {
+        for (name, child) in &self.children {
+            let child_path = if path.is_empty() {
+                name.clone()
+            } else {
+                format!("{}/{}", path, name)
+            };
+            writeln!(
+                writer,
+                "  {:?} [label={:?}, width={:.2}, height={:.2}];",
+                child_path,
+                format!("{}\n{}", name, format::cost(child.monthly_cost)),
+                dot_size(child.monthly_cost, total),
+                dot_size(child.monthly_cost, total) * 0.6
+            )?;
+            writeln!(
+                writer,
+                "  {:?} -> {:?};",
+                if path.is_empty() { DOT_ROOT } else { path },
+                child_path
+            )?;
+            child.write_dot(writer, &child_path, total)?;
+        }
+        Ok(())
+    }
+}
+
+/// id of the root node, which can't clash with a repository path as those
+/// never start with a space
+const DOT_ROOT: &str = " total";
+
+/// node width in inches, its area proportional to its share of the total
+fn dot_size(
+    cost: f64,
+    total: f64,
+) -> f64 {
+    const MIN: f64 = 0.75;
+    const MAX: f64 = 4.0;
+    if total > 0.0 {
+        MIN + (MAX - MIN) * (cost / total).sqrt()
+    } else {
+        MIN
+    }
+}
+
+/// repositories nested by their `/` delimited namespaces
+fn build(repos: &[Repo]) -> Node {
+    let mut root = Node::default();
+    for repo in repos {
+        root.add(repo);
+        let mut node = &mut root;
+        for segment in repo.name.split('/') {
+            node = node.children.entry(segment.to_string()).or_default();
+            node.add(repo);
+        }
+    }
+    root
+}
+
+/// writes repositories nested by their `/` delimited namespaces, each level
+/// showing the costs of everything beneath it
+pub fn write(
+    writer: impl Write,
+    repos: &[Repo],
+) -> Result<()> {
+    let root = build(repos);
+    let mut writer = TabWriter::new(writer);
+    writeln!(
+        writer,
+        "total\t{}\t=> {}",
+        format::cost(root.monthly_cost),
+        format::cost(root.monthly_capped_cost)
+    )?;
+    root.write(&mut writer, "")?;
+    writer.flush()
+}
+
+/// writes the namespace hierarchy as a graphviz digraph, for `dot -Tpng`,
+/// nodes sized by their monthly cost
+pub fn write_dot(
+    mut writer: impl Write,
+    repos: &[Repo],
+) -> Result<()> {
+    let root = build(repos);
+    writeln!(writer, "digraph repositories {{")?;
+    writeln!(writer, "  rankdir=LR;")?;
+    writeln!(writer, "  node [shape=box, fixedsize=true];")?;
+    writeln!(
+        writer,
+        "  {:?} [label={:?}, width={:.2}, height={:.2}];",
+        DOT_ROOT,
+        format!("total\n{}", format::cost(root.monthly_cost)),
+        dot_size(root.monthly_cost, root.monthly_cost),
+        dot_size(root.monthly_cost, root.monthly_cost) * 0.6
+    )?;
+    root.write_dot(&mut writer, "", root.monthly_cost)?;
+    writeln!(writer, "}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn namespaces_roll_up_their_repositories() {
+        let root = build(&[
+            crate::tests::repo("team/api"),
+            crate::tests::repo("team/web"),
+            crate::tests::repo("other"),
+        ]);
+        assert_eq!(root.children.keys().collect::<Vec<_>>(), ["other", "team"]);
+        let team = &root.children["team"];
+        assert_eq!(team.children.keys().collect::<Vec<_>>(), ["api", "web"]);
+        assert!((team.monthly_cost - 2.0 * team.children["api"].monthly_cost).abs() < 1e-9);
+        assert!((root.monthly_cost - 3.0 * root.children["other"].monthly_cost).abs() < 1e-9);
+    }
+
+    #[test]
+    fn writes_branches_beneath_the_total() {
+        let mut out = Vec::new();
+        write(
+            &mut out,
+            &[crate::tests::repo("team/api"), crate::tests::repo("web")],
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let names = out
+            .lines()
+            .map(|line| line.split('$').next().unwrap().trim_end())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["total", "├── team", "│   └── api", "└── web"]);
+    }
+
+    #[test]
+    fn dot_nodes_grow_with_their_share() {
+        assert_eq!(dot_size(0.0, 0.0), 0.75);
+        assert_eq!(dot_size(0.0, 10.0), 0.75);
+        assert_eq!(dot_size(10.0, 10.0), 4.0);
+        assert!(dot_size(2.5, 10.0) > dot_size(1.0, 10.0));
+    }
+
+    #[test]
+    fn dot_edges_link_paths_to_the_root() {
+        let mut out = Vec::new();
+        write_dot(&mut out, &[crate::tests::repo("team/api")]).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("digraph repositories {\n"));
+        assert!(out.contains("  \" total\" -> \"team\";\n"));
+        assert!(out.contains("  \"team\" -> \"team/api\";\n"));
+        assert!(out.ends_with("}\n"));
+    }
+}